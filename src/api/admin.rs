@@ -0,0 +1,561 @@
+//! Key configuration and token management APIs.
+
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+use crate::config::ClientConfig;
+use crate::endpoint::Endpoints;
+#[cfg(feature = "async")]
+use crate::http::AsyncHttpTransport;
+use crate::http::{HttpClient, Response};
+use crate::types::admin::{
+    FormattedKeyConfig, IncrementKeyConfig, KeyConfig, KeyRequest, SnowflakeKeyConfig,
+};
+use crate::types::token::{
+    IssueTokenRequest, IssuedToken, ScopeTokenRequest, TokenInfo, TokenRequest,
+};
+use crate::{Error, Result};
+
+use super::request::{self, Executor};
+use super::urlencoding;
+
+/// Admin API for managing ID key configurations and key tokens.
+///
+/// Requests are authorized with the admin token rather than the key token.
+#[derive(Debug)]
+pub struct AdminApi<'a, C> {
+    executor: Executor<'a, C>,
+}
+
+impl<'a, C> AdminApi<'a, C> {
+    /// Create a new admin API instance.
+    pub(crate) const fn new(
+        config: &'a ClientConfig,
+        endpoints: &'a Endpoints,
+        admin_token: &'a str,
+        client: &'a C,
+    ) -> Self {
+        Self {
+            executor: Executor::new(config, endpoints, admin_token, client),
+        }
+    }
+
+    /// Manage auto-increment keys.
+    #[must_use]
+    pub const fn increment(&self) -> KeyAdminApi<'a, C, IncrementKeyConfig> {
+        self.keys()
+    }
+
+    /// Manage snowflake keys.
+    #[must_use]
+    pub const fn snowflake(&self) -> KeyAdminApi<'a, C, SnowflakeKeyConfig> {
+        self.keys()
+    }
+
+    /// Manage formatted keys.
+    #[must_use]
+    pub const fn formatted(&self) -> KeyAdminApi<'a, C, FormattedKeyConfig> {
+        self.keys()
+    }
+
+    /// Manage key tokens.
+    #[must_use]
+    pub const fn tokens(&self) -> TokenAdminApi<'a, C> {
+        TokenAdminApi {
+            admin: self.reborrow(),
+        }
+    }
+
+    const fn keys<K>(&self) -> KeyAdminApi<'a, C, K> {
+        KeyAdminApi {
+            admin: self.reborrow(),
+            kind: PhantomData,
+        }
+    }
+
+    const fn reborrow(&self) -> Self {
+        Self {
+            executor: self.executor,
+        }
+    }
+}
+
+/// Admin API for the keys of one ID type, see [`AdminApi`].
+#[derive(Debug)]
+pub struct KeyAdminApi<'a, C, K> {
+    admin: AdminApi<'a, C>,
+    kind: PhantomData<K>,
+}
+
+impl<C, K: KeyConfig> KeyAdminApi<'_, C, K> {
+    fn key_path(key: &str) -> String {
+        format!("/v1/admin/{}?key={}", K::KIND, urlencoding::encode(key))
+    }
+
+    fn action_path(action: &str) -> String {
+        format!("/v1/admin/{}/{action}", K::KIND)
+    }
+
+    fn decode<T: DeserializeOwned>(response: &Response, key: &str) -> Result<T> {
+        request::decode(response, key, || Error::ConfigNotFound(key.to_string()))
+    }
+
+    fn decode_empty(response: &Response, key: &str) -> Result<()> {
+        request::decode_empty(response, key, || Error::ConfigNotFound(key.to_string()))
+    }
+
+    fn delete_body(key: &str) -> Result<String> {
+        Ok(serde_json::to_string(&KeyRequest {
+            key: key.to_string(),
+        })?)
+    }
+}
+
+impl<C: HttpClient, K: KeyConfig> KeyAdminApi<'_, C, K> {
+    /// Create a key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the key already exists.
+    pub fn create(&self, config: &K) -> Result<K> {
+        let body = serde_json::to_string(config)?;
+        // Creating a key twice fails the second time, so only safe failures are retried.
+        let response = self
+            .admin
+            .executor
+            .post(&Self::action_path("create"), &body, false)?;
+        Self::decode(&response, config.key())
+    }
+
+    /// Get the configuration of a key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigNotFound`] if the key does not exist, or an
+    /// error if the request fails.
+    pub fn get(&self, key: &str) -> Result<K> {
+        let response = self.admin.executor.get(&Self::key_path(key), true)?;
+        Self::decode(&response, key)
+    }
+
+    /// Replace the configuration of an existing key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigNotFound`] if the key does not exist, or an
+    /// error if the request fails.
+    pub fn update(&self, config: &K) -> Result<K> {
+        let body = serde_json::to_string(config)?;
+        let response = self
+            .admin
+            .executor
+            .post(&Self::action_path("update"), &body, true)?;
+        Self::decode(&response, config.key())
+    }
+
+    /// Delete a key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigNotFound`] if the key does not exist, or an
+    /// error if the request fails.
+    pub fn delete(&self, key: &str) -> Result<()> {
+        let body = Self::delete_body(key)?;
+        let response = self
+            .admin
+            .executor
+            .post(&Self::action_path("delete"), &body, true)?;
+        Self::decode_empty(&response, key)
+    }
+
+    /// List all keys of this type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub fn list(&self) -> Result<Vec<K>> {
+        let response = self.admin.executor.get(&Self::action_path("list"), true)?;
+        Self::decode(&response, K::KIND)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync, K: KeyConfig + Sync> KeyAdminApi<'_, C, K> {
+    /// Create a key asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the key already exists.
+    pub async fn create_async(&self, config: &K) -> Result<K> {
+        let body = serde_json::to_string(config)?;
+        // Creating a key twice fails the second time, so only safe failures are retried.
+        let response = self
+            .admin
+            .executor
+            .post_async(&Self::action_path("create"), &body, false)
+            .await?;
+        Self::decode(&response, config.key())
+    }
+
+    /// Get the configuration of a key asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigNotFound`] if the key does not exist, or an
+    /// error if the request fails.
+    pub async fn get_async(&self, key: &str) -> Result<K> {
+        let response = self
+            .admin
+            .executor
+            .get_async(&Self::key_path(key), true)
+            .await?;
+        Self::decode(&response, key)
+    }
+
+    /// Replace the configuration of an existing key asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigNotFound`] if the key does not exist, or an
+    /// error if the request fails.
+    pub async fn update_async(&self, config: &K) -> Result<K> {
+        let body = serde_json::to_string(config)?;
+        let response = self
+            .admin
+            .executor
+            .post_async(&Self::action_path("update"), &body, true)
+            .await?;
+        Self::decode(&response, config.key())
+    }
+
+    /// Delete a key asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ConfigNotFound`] if the key does not exist, or an
+    /// error if the request fails.
+    pub async fn delete_async(&self, key: &str) -> Result<()> {
+        let body = Self::delete_body(key)?;
+        let response = self
+            .admin
+            .executor
+            .post_async(&Self::action_path("delete"), &body, true)
+            .await?;
+        Self::decode_empty(&response, key)
+    }
+
+    /// List all keys of this type asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn list_async(&self) -> Result<Vec<K>> {
+        let response = self
+            .admin
+            .executor
+            .get_async(&Self::action_path("list"), true)
+            .await?;
+        Self::decode(&response, K::KIND)
+    }
+}
+
+/// Admin API for issuing, scoping and revoking key tokens, see [`AdminApi`].
+#[derive(Debug)]
+pub struct TokenAdminApi<'a, C> {
+    admin: AdminApi<'a, C>,
+}
+
+impl<C> TokenAdminApi<'_, C> {
+    const ISSUE_PATH: &'static str = "/v1/admin/token/issue";
+    const LIST_PATH: &'static str = "/v1/admin/token/list";
+    const SCOPE_PATH: &'static str = "/v1/admin/token/scope";
+    const REVOKE_PATH: &'static str = "/v1/admin/token/revoke";
+
+    fn decode<T: DeserializeOwned>(response: &Response, id: &str) -> Result<T> {
+        request::decode(response, id, || token_not_found(response, id))
+    }
+
+    fn scope_body(id: &str, allowed_keys: &[String]) -> Result<String> {
+        Ok(serde_json::to_string(&ScopeTokenRequest {
+            id: id.to_string(),
+            allowed_keys: allowed_keys.to_vec(),
+        })?)
+    }
+
+    fn revoke_body(id: &str) -> Result<String> {
+        Ok(serde_json::to_string(&TokenRequest { id: id.to_string() })?)
+    }
+}
+
+impl<C: HttpClient> TokenAdminApi<'_, C> {
+    /// Issue a new key token.
+    ///
+    /// The token secret is only returned here; later calls only see its
+    /// [`TokenInfo`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub fn issue(&self, request: &IssueTokenRequest) -> Result<IssuedToken> {
+        let body = serde_json::to_string(request)?;
+        // Issuing twice creates two tokens, so only safe failures are retried.
+        let response = self.admin.executor.post(Self::ISSUE_PATH, &body, false)?;
+        Self::decode(&response, "")
+    }
+
+    /// List the metadata of all key tokens.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub fn list(&self) -> Result<Vec<TokenInfo>> {
+        let response = self.admin.executor.get(Self::LIST_PATH, true)?;
+        Self::decode(&response, "")
+    }
+
+    /// Replace the keys a token may access.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token does not exist or the request fails.
+    pub fn scope(&self, id: &str, allowed_keys: &[String]) -> Result<TokenInfo> {
+        let body = Self::scope_body(id, allowed_keys)?;
+        let response = self.admin.executor.post(Self::SCOPE_PATH, &body, true)?;
+        Self::decode(&response, id)
+    }
+
+    /// Revoke a token so it can no longer be used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token does not exist or the request fails.
+    pub fn revoke(&self, id: &str) -> Result<()> {
+        let body = Self::revoke_body(id)?;
+        let response = self.admin.executor.post(Self::REVOKE_PATH, &body, true)?;
+        request::decode_empty(&response, id, || token_not_found(&response, id))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> TokenAdminApi<'_, C> {
+    /// Issue a new key token asynchronously.
+    ///
+    /// See [`issue`](Self::issue) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn issue_async(&self, request: &IssueTokenRequest) -> Result<IssuedToken> {
+        let body = serde_json::to_string(request)?;
+        // Issuing twice creates two tokens, so only safe failures are retried.
+        let response = self
+            .admin
+            .executor
+            .post_async(Self::ISSUE_PATH, &body, false)
+            .await?;
+        Self::decode(&response, "")
+    }
+
+    /// List the metadata of all key tokens asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn list_async(&self) -> Result<Vec<TokenInfo>> {
+        let response = self.admin.executor.get_async(Self::LIST_PATH, true).await?;
+        Self::decode(&response, "")
+    }
+
+    /// Replace the keys a token may access asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token does not exist or the request fails.
+    pub async fn scope_async(&self, id: &str, allowed_keys: &[String]) -> Result<TokenInfo> {
+        let body = Self::scope_body(id, allowed_keys)?;
+        let response = self
+            .admin
+            .executor
+            .post_async(Self::SCOPE_PATH, &body, true)
+            .await?;
+        Self::decode(&response, id)
+    }
+
+    /// Revoke a token asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token does not exist or the request fails.
+    pub async fn revoke_async(&self, id: &str) -> Result<()> {
+        let body = Self::revoke_body(id)?;
+        let response = self
+            .admin
+            .executor
+            .post_async(Self::REVOKE_PATH, &body, true)
+            .await?;
+        request::decode_empty(&response, id, || token_not_found(&response, id))
+    }
+}
+
+/// There is no dedicated error for unknown tokens, so keep the server's.
+fn token_not_found(response: &Response, id: &str) -> Error {
+    match request::api_error(response, id) {
+        Error::Api { code, message } if message.is_empty() => Error::Api {
+            code,
+            message: format!("Token not found: {id}"),
+        },
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{IdBuilderClient, IssueTokenRequest};
+
+    /// Records requests and answers with a canned response.
+    struct RecordingHttpClient {
+        requests: Mutex<Vec<(String, String, Option<String>)>>,
+        response: Response,
+    }
+
+    impl RecordingHttpClient {
+        fn new(status: u16, body: &str) -> Self {
+            Self {
+                requests: Mutex::new(Vec::new()),
+                response: Response::new(status, body.to_string()),
+            }
+        }
+
+        fn respond(&self) -> Response {
+            Response::new(self.response.status, self.response.body.clone())
+        }
+    }
+
+    impl HttpClient for RecordingHttpClient {
+        fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((url.to_string(), headers[0].1.to_string(), None));
+            Ok(self.respond())
+        }
+
+        fn post(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response> {
+            self.requests.lock().unwrap().push((
+                url.to_string(),
+                headers[0].1.to_string(),
+                Some(body.to_string()),
+            ));
+            Ok(self.respond())
+        }
+    }
+
+    fn client(http: RecordingHttpClient) -> IdBuilderClient<RecordingHttpClient> {
+        let config = ClientConfig::new("http://localhost:8080")
+            .with_key_token("key-token")
+            .with_admin_token("admin-token");
+        IdBuilderClient::with_http_client(config, http)
+    }
+
+    #[test]
+    fn test_create_posts_config_with_admin_token() {
+        let client = client(RecordingHttpClient::new(
+            200,
+            r#"{"code":0,"message":"success","data":{"key":"order-id","start":1,"step":1}}"#,
+        ));
+
+        let created = client
+            .admin()
+            .increment()
+            .create(&IncrementKeyConfig::new("order-id"))
+            .unwrap();
+        assert_eq!(created, IncrementKeyConfig::new("order-id"));
+
+        let requests = client.http_client().requests.lock().unwrap().clone();
+        assert_eq!(
+            requests[0],
+            (
+                "http://localhost:8080/v1/admin/increment/create".to_string(),
+                "admin-token".to_string(),
+                Some(r#"{"key":"order-id","start":1,"step":1}"#.to_string()),
+            )
+        );
+    }
+
+    #[test]
+    fn test_get_and_list_urls() {
+        let client = client(RecordingHttpClient::new(
+            200,
+            r#"{"code":0,"message":"success","data":[{"key":"invoice id","pattern":"INV-####"}]}"#,
+        ));
+
+        let keys = client.admin().formatted().list().unwrap();
+        assert_eq!(keys, [FormattedKeyConfig::new("invoice id", "INV-####")]);
+
+        // The canned list response does not parse as a single key.
+        assert!(client.admin().formatted().get("invoice id").is_err());
+
+        let requests = client.http_client().requests.lock().unwrap().clone();
+        assert_eq!(
+            requests[0].0,
+            "http://localhost:8080/v1/admin/formatted/list"
+        );
+        assert_eq!(
+            requests[1].0,
+            "http://localhost:8080/v1/admin/formatted?key=invoice%20id"
+        );
+    }
+
+    #[test]
+    fn test_delete_missing_key() {
+        let client = client(RecordingHttpClient::new(404, "not found"));
+
+        let result = client.admin().snowflake().delete("user-id");
+        assert!(matches!(result, Err(Error::ConfigNotFound(key)) if key == "user-id"));
+
+        let requests = client.http_client().requests.lock().unwrap().clone();
+        assert_eq!(requests[0].2.as_deref(), Some(r#"{"key":"user-id"}"#));
+    }
+
+    #[test]
+    fn test_issue_token() {
+        let client = client(RecordingHttpClient::new(
+            200,
+            r#"{"code":0,"message":"success","data":{"token":"secret","id":"tok-1","allowed_keys":["order-id"],"created_at":1704067200000}}"#,
+        ));
+
+        let issued = client
+            .admin()
+            .tokens()
+            .issue(&IssueTokenRequest::new(["order-id"]).with_description("checkout"))
+            .unwrap();
+        assert_eq!(issued.token, "secret");
+        assert_eq!(issued.info.allowed_keys, ["order-id"]);
+
+        let requests = client.http_client().requests.lock().unwrap().clone();
+        assert_eq!(requests[0].0, "http://localhost:8080/v1/admin/token/issue");
+        assert_eq!(
+            requests[0].2.as_deref(),
+            Some(r#"{"allowed_keys":["order-id"],"description":"checkout"}"#)
+        );
+    }
+
+    #[test]
+    fn test_revoke_unknown_token() {
+        let client = client(RecordingHttpClient::new(
+            404,
+            r#"{"code":40401,"message":"token not found","data":null}"#,
+        ));
+
+        let result = client.admin().tokens().revoke("tok-1");
+        assert!(matches!(result, Err(Error::Api { code: 40401, .. })));
+
+        let requests = client.http_client().requests.lock().unwrap().clone();
+        assert_eq!(requests[0].0, "http://localhost:8080/v1/admin/token/revoke");
+        assert_eq!(requests[0].2.as_deref(), Some(r#"{"id":"tok-1"}"#));
+    }
+}
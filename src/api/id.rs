@@ -1,36 +1,54 @@
 //! ID generation APIs.
 
-use crate::http::HttpClient;
-use crate::types::response::{
-    ApiResponse, FormattedIdResponse, IncrementIdResponse, SnowflakeIdResponse,
-};
+use std::time::Instant;
+
+use serde::de::DeserializeOwned;
+
+use crate::config::ClientConfig;
+use crate::endpoint::Endpoints;
+#[cfg(feature = "async")]
+use crate::http::AsyncHttpTransport;
+use crate::http::{HttpClient, Response};
+use crate::trace;
+use crate::types::request::WorkerLeaseRequest;
+use crate::types::response::{FormattedIdResponse, IncrementIdResponse, SnowflakeIdResponse};
 use crate::{Error, Result};
 
+use super::request::{self, Executor};
+use super::urlencoding;
+
 /// Auto-increment ID generation API.
 #[derive(Debug)]
-pub struct IncrementApi<'a, C: HttpClient> {
-    base_url: &'a str,
-    key_token: &'a str,
-    client: &'a C,
+pub struct IncrementApi<'a, C> {
+    executor: Executor<'a, C>,
     key: String,
 }
 
-impl<'a, C: HttpClient> IncrementApi<'a, C> {
+impl<'a, C> IncrementApi<'a, C> {
     /// Create a new increment API instance.
     pub(crate) fn new(
-        base_url: &'a str,
+        config: &'a ClientConfig,
+        endpoints: &'a Endpoints,
         key_token: &'a str,
         client: &'a C,
         key: impl Into<String>,
     ) -> Self {
         Self {
-            base_url,
-            key_token,
-            client,
+            executor: Executor::new(config, endpoints, key_token, client),
             key: key.into(),
         }
     }
 
+    fn path(&self, count: u32) -> String {
+        format!(
+            "/v1/id/increment?key={}&size={}",
+            urlencoding::encode(&self.key),
+            count
+        )
+    }
+}
+
+impl<C: HttpClient> IncrementApi<'_, C> {
     /// Generate a single auto-increment ID.
     ///
     /// # Errors
@@ -38,10 +56,7 @@ impl<'a, C: HttpClient> IncrementApi<'a, C> {
     /// Returns an error if the request fails or the sequence is exhausted.
     pub fn generate_one(&self) -> Result<i64> {
         let ids = self.generate(1)?;
-        ids.into_iter().next().ok_or_else(|| Error::Api {
-            code: 0,
-            message: "No IDs returned".to_string(),
-        })
+        first_id(ids)
     }
 
     /// Generate multiple auto-increment IDs.
@@ -54,73 +69,105 @@ impl<'a, C: HttpClient> IncrementApi<'a, C> {
     ///
     /// Returns an error if the request fails or the sequence is exhausted.
     pub fn generate(&self, count: u32) -> Result<Vec<i64>> {
-        let url = format!(
-            "{}/v1/id/increment?key={}&size={}",
-            self.base_url,
-            urlencoding::encode(&self.key),
-            count
-        );
-        let headers = [("Authorization", self.key_token)];
-
-        let response = self.client.get(&url, &headers)?;
-
-        match response.status {
-            200 => {
-                let api_resp: ApiResponse<IncrementIdResponse> =
-                    serde_json::from_str(&response.body)?;
-                Ok(api_resp.into_result()?.ids)
-            }
-            401 => Err(Error::Unauthorized),
-            403 => Err(Error::Forbidden),
-            404 => Err(Error::ConfigNotFound(self.key.clone())),
-            429 => Err(Error::RateLimited),
-            _ => {
-                let api_resp: ApiResponse<()> = serde_json::from_str(&response.body)
-                    .unwrap_or_else(|_| ApiResponse {
-                        code: response.status.into(),
-                        message: response.body.clone(),
-                        data: None,
-                    });
-
-                // Check for sequence exhausted error
-                if api_resp.message.to_lowercase().contains("exhausted") {
-                    return Err(Error::SequenceExhausted(self.key.clone()));
-                }
-
-                Err(Error::Api {
-                    code: api_resp.code,
-                    message: api_resp.message,
-                })
-            }
-        }
+        let span = trace::call_span("increment.generate", &self.key, Some(count));
+        let started = Instant::now();
+        let outcome = trace::in_call(&span, || {
+            let response = self.executor.allocate(&self.path(count))?;
+            decode::<IncrementIdResponse>(&response, &self.key).map(|data| data.ids)
+        });
+        self.executor
+            .metrics()
+            .record_call(&self.key, started, &outcome, Vec::len);
+        outcome
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> IncrementApi<'_, C> {
+    /// Generate a single auto-increment ID asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub async fn generate_one_async(&self) -> Result<i64> {
+        let ids = self.generate_async(1).await?;
+        first_id(ids)
+    }
+
+    /// Generate multiple auto-increment IDs asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of IDs to generate (max 1000)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub async fn generate_async(&self, count: u32) -> Result<Vec<i64>> {
+        let span = trace::call_span("increment.generate", &self.key, Some(count));
+        let started = Instant::now();
+        let outcome = trace::in_call_async(&span, async {
+            let response = self.executor.allocate_async(&self.path(count)).await?;
+            decode::<IncrementIdResponse>(&response, &self.key).map(|data| data.ids)
+        })
+        .await;
+        self.executor
+            .metrics()
+            .record_call(&self.key, started, &outcome, Vec::len);
+        outcome
     }
 }
 
 /// Snowflake ID generation API.
 #[derive(Debug)]
-pub struct SnowflakeApi<'a, C: HttpClient> {
-    base_url: &'a str,
-    key_token: &'a str,
-    client: &'a C,
+pub struct SnowflakeApi<'a, C> {
+    executor: Executor<'a, C>,
     key: String,
 }
 
-impl<'a, C: HttpClient> SnowflakeApi<'a, C> {
+impl<'a, C> SnowflakeApi<'a, C> {
     /// Create a new snowflake API instance.
     pub(crate) fn new(
-        base_url: &'a str,
+        config: &'a ClientConfig,
+        endpoints: &'a Endpoints,
         key_token: &'a str,
         client: &'a C,
         key: impl Into<String>,
     ) -> Self {
         Self {
-            base_url,
-            key_token,
-            client,
+            executor: Executor::new(config, endpoints, key_token, client),
             key: key.into(),
         }
     }
 
+    fn path(&self) -> String {
+        format!("/v1/id/snowflake?key={}", urlencoding::encode(&self.key))
+    }
+
+    fn lease_path(action: &str) -> String {
+        format!("/v1/id/snowflake/{action}")
+    }
+
+    fn lease_body(&self, worker_id: u32) -> Result<String> {
+        let request = WorkerLeaseRequest {
+            key: self.key.clone(),
+            worker_id,
+        };
+        Ok(serde_json::to_string(&request)?)
+    }
+
+    /// The server answers 409 or 410 for a worker ID it no longer holds.
+    fn decode_lease(&self, response: &Response) -> Result<()> {
+        if matches!(response.status, 409 | 410) {
+            return Err(Error::LeaseLost(self.key.clone()));
+        }
+        request::decode_empty(response, &self.key, || {
+            Error::ConfigNotFound(self.key.clone())
+        })
+    }
+}
+
+impl<C: HttpClient> SnowflakeApi<'_, C> {
     /// Get the snowflake configuration for local ID generation.
     ///
     /// The returned configuration contains a worker ID assigned by the server
@@ -131,65 +178,137 @@ impl<'a, C: HttpClient> SnowflakeApi<'a, C> {
     ///
     /// Returns an error if the request fails or the configuration doesn't exist.
     pub fn get_config(&self) -> Result<SnowflakeIdResponse> {
-        let url = format!(
-            "{}/v1/id/snowflake?key={}",
-            self.base_url,
-            urlencoding::encode(&self.key)
-        );
-        let headers = [("Authorization", self.key_token)];
-
-        let response = self.client.get(&url, &headers)?;
-
-        match response.status {
-            200 => {
-                let api_resp: ApiResponse<SnowflakeIdResponse> =
-                    serde_json::from_str(&response.body)?;
-                api_resp.into_result()
-            }
-            401 => Err(Error::Unauthorized),
-            403 => Err(Error::Forbidden),
-            404 => Err(Error::ConfigNotFound(self.key.clone())),
-            _ => {
-                let api_resp: ApiResponse<()> = serde_json::from_str(&response.body)
-                    .unwrap_or_else(|_| ApiResponse {
-                        code: response.status.into(),
-                        message: response.body.clone(),
-                        data: None,
-                    });
-                Err(Error::Api {
-                    code: api_resp.code,
-                    message: api_resp.message,
-                })
-            }
-        }
+        let span = trace::call_span("snowflake.get_config", &self.key, None);
+        let started = Instant::now();
+        let outcome = trace::in_call(&span, || {
+            let response = self.executor.allocate(&self.path())?;
+            decode(&response, &self.key)
+        });
+        self.executor
+            .metrics()
+            .record_call(&self.key, started, &outcome, |_| 0);
+        outcome
+    }
+
+    /// Renew the lease on a worker ID assigned by [`get_config`](Self::get_config).
+    ///
+    /// Worker IDs are leased: the server may reassign a worker ID whose lease
+    /// is not renewed in time. See [`SnowflakeLease`](crate::SnowflakeLease)
+    /// for a lease that is renewed automatically.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LeaseLost`] if the server no longer holds the worker ID
+    /// for this client, or an error if the request fails.
+    pub fn heartbeat(&self, worker_id: u32) -> Result<()> {
+        self.post_lease("heartbeat", worker_id)
+    }
+
+    /// Release a worker ID so the server can reassign it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub fn release(&self, worker_id: u32) -> Result<()> {
+        self.post_lease("release", worker_id)
+    }
+
+    fn post_lease(&self, action: &str, worker_id: u32) -> Result<()> {
+        let body = self.lease_body(worker_id)?;
+        // Renewing or releasing a lease twice has the same effect as once.
+        let response = self.executor.post(&Self::lease_path(action), &body, true)?;
+        self.decode_lease(&response)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> SnowflakeApi<'_, C> {
+    /// Get the snowflake configuration for local ID generation asynchronously.
+    ///
+    /// See [`get_config`](Self::get_config) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the configuration doesn't exist.
+    pub async fn get_config_async(&self) -> Result<SnowflakeIdResponse> {
+        let span = trace::call_span("snowflake.get_config", &self.key, None);
+        let started = Instant::now();
+        let outcome = trace::in_call_async(&span, async {
+            let response = self.executor.allocate_async(&self.path()).await?;
+            decode(&response, &self.key)
+        })
+        .await;
+        self.executor
+            .metrics()
+            .record_call(&self.key, started, &outcome, |_| 0);
+        outcome
+    }
+
+    /// Renew the lease on a worker ID asynchronously.
+    ///
+    /// See [`heartbeat`](Self::heartbeat) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LeaseLost`] if the server no longer holds the worker ID
+    /// for this client, or an error if the request fails.
+    pub async fn heartbeat_async(&self, worker_id: u32) -> Result<()> {
+        self.post_lease_async("heartbeat", worker_id).await
+    }
+
+    /// Release a worker ID asynchronously.
+    ///
+    /// See [`release`](Self::release) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn release_async(&self, worker_id: u32) -> Result<()> {
+        self.post_lease_async("release", worker_id).await
+    }
+
+    async fn post_lease_async(&self, action: &str, worker_id: u32) -> Result<()> {
+        let body = self.lease_body(worker_id)?;
+        let response = self
+            .executor
+            .post_async(&Self::lease_path(action), &body, true)
+            .await?;
+        self.decode_lease(&response)
     }
 }
 
 /// Formatted string ID generation API.
 #[derive(Debug)]
-pub struct FormattedApi<'a, C: HttpClient> {
-    base_url: &'a str,
-    key_token: &'a str,
-    client: &'a C,
+pub struct FormattedApi<'a, C> {
+    executor: Executor<'a, C>,
     key: String,
 }
 
-impl<'a, C: HttpClient> FormattedApi<'a, C> {
+impl<'a, C> FormattedApi<'a, C> {
     /// Create a new formatted API instance.
     pub(crate) fn new(
-        base_url: &'a str,
+        config: &'a ClientConfig,
+        endpoints: &'a Endpoints,
         key_token: &'a str,
         client: &'a C,
         key: impl Into<String>,
     ) -> Self {
         Self {
-            base_url,
-            key_token,
-            client,
+            executor: Executor::new(config, endpoints, key_token, client),
             key: key.into(),
         }
     }
 
+    fn path(&self, count: u32) -> String {
+        format!(
+            "/v1/id/formatted?key={}&size={}",
+            urlencoding::encode(&self.key),
+            count
+        )
+    }
+}
+
+impl<C: HttpClient> FormattedApi<'_, C> {
     /// Generate a single formatted ID.
     ///
     /// # Errors
@@ -197,10 +316,7 @@ impl<'a, C: HttpClient> FormattedApi<'a, C> {
     /// Returns an error if the request fails or the sequence is exhausted.
     pub fn generate_one(&self) -> Result<String> {
         let ids = self.generate(1)?;
-        ids.into_iter().next().ok_or_else(|| Error::Api {
-            code: 0,
-            message: "No IDs returned".to_string(),
-        })
+        first_id(ids)
     }
 
     /// Generate multiple formatted IDs.
@@ -213,73 +329,64 @@ impl<'a, C: HttpClient> FormattedApi<'a, C> {
     ///
     /// Returns an error if the request fails or the sequence is exhausted.
     pub fn generate(&self, count: u32) -> Result<Vec<String>> {
-        let url = format!(
-            "{}/v1/id/formatted?key={}&size={}",
-            self.base_url,
-            urlencoding::encode(&self.key),
-            count
-        );
-        let headers = [("Authorization", self.key_token)];
-
-        let response = self.client.get(&url, &headers)?;
-
-        match response.status {
-            200 => {
-                let api_resp: ApiResponse<FormattedIdResponse> =
-                    serde_json::from_str(&response.body)?;
-                Ok(api_resp.into_result()?.ids)
-            }
-            401 => Err(Error::Unauthorized),
-            403 => Err(Error::Forbidden),
-            404 => Err(Error::ConfigNotFound(self.key.clone())),
-            429 => Err(Error::RateLimited),
-            _ => {
-                let api_resp: ApiResponse<()> = serde_json::from_str(&response.body)
-                    .unwrap_or_else(|_| ApiResponse {
-                        code: response.status.into(),
-                        message: response.body.clone(),
-                        data: None,
-                    });
-
-                // Check for sequence exhausted error
-                if api_resp.message.to_lowercase().contains("exhausted") {
-                    return Err(Error::SequenceExhausted(self.key.clone()));
-                }
-
-                Err(Error::Api {
-                    code: api_resp.code,
-                    message: api_resp.message,
-                })
-            }
-        }
+        let span = trace::call_span("formatted.generate", &self.key, Some(count));
+        let started = Instant::now();
+        let outcome = trace::in_call(&span, || {
+            let response = self.executor.allocate(&self.path(count))?;
+            decode::<FormattedIdResponse>(&response, &self.key).map(|data| data.ids)
+        });
+        self.executor
+            .metrics()
+            .record_call(&self.key, started, &outcome, Vec::len);
+        outcome
     }
 }
 
-/// URL encoding helper.
-mod urlencoding {
-    /// Percent-encode a string for use in URL query parameters.
-    pub fn encode(input: &str) -> String {
-        let mut result = String::with_capacity(input.len() * 3);
-        for byte in input.bytes() {
-            match byte {
-                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
-                    result.push(byte as char);
-                }
-                _ => {
-                    result.push('%');
-                    result.push(
-                        char::from_digit(u32::from(byte >> 4), 16)
-                            .unwrap()
-                            .to_ascii_uppercase(),
-                    );
-                    result.push(
-                        char::from_digit(u32::from(byte & 0xF), 16)
-                            .unwrap()
-                            .to_ascii_uppercase(),
-                    );
-                }
-            }
-        }
-        result
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> FormattedApi<'_, C> {
+    /// Generate a single formatted ID asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub async fn generate_one_async(&self) -> Result<String> {
+        let ids = self.generate_async(1).await?;
+        first_id(ids)
+    }
+
+    /// Generate multiple formatted IDs asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of IDs to generate (max 1000)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub async fn generate_async(&self, count: u32) -> Result<Vec<String>> {
+        let span = trace::call_span("formatted.generate", &self.key, Some(count));
+        let started = Instant::now();
+        let outcome = trace::in_call_async(&span, async {
+            let response = self.executor.allocate_async(&self.path(count)).await?;
+            decode::<FormattedIdResponse>(&response, &self.key).map(|data| data.ids)
+        })
+        .await;
+        self.executor
+            .metrics()
+            .record_call(&self.key, started, &outcome, Vec::len);
+        outcome
     }
 }
+
+/// Decode the data of a response about `key`.
+fn decode<T: DeserializeOwned>(response: &Response, key: &str) -> Result<T> {
+    request::decode(response, key, || Error::ConfigNotFound(key.to_string()))
+}
+
+/// Take the first ID from a generated batch.
+fn first_id<T>(ids: Vec<T>) -> Result<T> {
+    ids.into_iter().next().ok_or_else(|| Error::Api {
+        code: 0,
+        message: "No IDs returned".to_string(),
+    })
+}
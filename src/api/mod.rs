@@ -1,5 +1,9 @@
 //! API endpoint implementations.
 
+mod admin;
 mod id;
+mod request;
+pub(crate) mod urlencoding;
 
+pub use admin::{AdminApi, KeyAdminApi, TokenAdminApi};
 pub use id::{FormattedApi, IncrementApi, SnowflakeApi};
@@ -0,0 +1,264 @@
+//! Request execution shared by all APIs.
+//!
+//! [`Executor`] sends a request to the configured endpoints with the
+//! authorization header, failover and retries applied, and the `decode`
+//! functions map the response to data or an [`Error`]. Every endpoint goes
+//! through both, so status codes and server error codes are reported the same
+//! way everywhere.
+
+use serde::de::{DeserializeOwned, IgnoredAny};
+
+use crate::config::ClientConfig;
+use crate::endpoint::Endpoints;
+#[cfg(feature = "async")]
+use crate::http::AsyncHttpTransport;
+use crate::http::{HttpClient, Response};
+use crate::metrics::Metrics;
+use crate::retry;
+use crate::trace;
+use crate::types::response::ApiResponse;
+use crate::{Error, RateLimit, Result};
+
+/// Sends authorized requests to the configured endpoints.
+#[derive(Debug)]
+pub struct Executor<'a, C> {
+    config: &'a ClientConfig,
+    endpoints: &'a Endpoints,
+    token: &'a str,
+    client: &'a C,
+}
+
+impl<'a, C> Executor<'a, C> {
+    /// Create an executor authorizing requests with `token`.
+    pub const fn new(
+        config: &'a ClientConfig,
+        endpoints: &'a Endpoints,
+        token: &'a str,
+        client: &'a C,
+    ) -> Self {
+        Self {
+            config,
+            endpoints,
+            token,
+            client,
+        }
+    }
+
+    /// Get the metrics configured for the client.
+    pub const fn metrics(&self) -> &'a Metrics {
+        &self.config.metrics
+    }
+}
+
+impl<C> Clone for Executor<'_, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for Executor<'_, C> {}
+
+impl<C: HttpClient> Executor<'_, C> {
+    /// Send a GET request for `path`, which includes the query string.
+    pub fn get(&self, path: &str, idempotent: bool) -> Result<Response> {
+        let headers = [("Authorization", self.token)];
+        let mut attempt = 0;
+        retry::execute(
+            &self.config.retry_policy,
+            self.config.retries,
+            idempotent,
+            || {
+                attempt += 1;
+                self.endpoints.send(|base_url| {
+                    let span = trace::request_span(attempt, base_url);
+                    trace::send(&span, || {
+                        self.client.get(&format!("{base_url}{path}"), &headers)
+                    })
+                })
+            },
+        )
+    }
+
+    /// Send a GET request for `path` that allocates IDs or a worker ID.
+    ///
+    /// Allocation is not idempotent: if the server processed a request whose
+    /// response was lost, repeating it allocates again and the first result
+    /// is skipped. Only failures where the server is known not to have
+    /// processed the request are retried, see
+    /// [`RetryClass::is_safe`](crate::RetryClass::is_safe).
+    pub fn allocate(&self, path: &str) -> Result<Response> {
+        self.get(path, false)
+    }
+
+    /// Send a POST request for `path` with a JSON body.
+    pub fn post(&self, path: &str, body: &str, idempotent: bool) -> Result<Response> {
+        let headers = [("Authorization", self.token)];
+        let mut attempt = 0;
+        retry::execute(
+            &self.config.retry_policy,
+            self.config.retries,
+            idempotent,
+            || {
+                attempt += 1;
+                self.endpoints.send(|base_url| {
+                    let span = trace::request_span(attempt, base_url);
+                    trace::send(&span, || {
+                        self.client
+                            .post(&format!("{base_url}{path}"), &headers, body)
+                    })
+                })
+            },
+        )
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> Executor<'_, C> {
+    /// Send a GET request for `path` asynchronously.
+    pub async fn get_async(&self, path: &str, idempotent: bool) -> Result<Response> {
+        let headers = [("Authorization", self.token)];
+        let mut attempt = 0;
+        retry::execute_async(
+            &self.config.retry_policy,
+            self.config.retries,
+            idempotent,
+            || {
+                attempt += 1;
+                self.endpoints.send_async(move |base_url| {
+                    let span = trace::request_span(attempt, &base_url);
+                    let url = format!("{base_url}{path}");
+                    trace::send_async(span, async move { self.client.get(&url, &headers).await })
+                })
+            },
+        )
+        .await
+    }
+
+    /// Send a GET request for `path` that allocates IDs or a worker ID
+    /// asynchronously, see [`allocate`](Self::allocate).
+    pub async fn allocate_async(&self, path: &str) -> Result<Response> {
+        self.get_async(path, false).await
+    }
+
+    /// Send a POST request for `path` with a JSON body asynchronously.
+    pub async fn post_async(&self, path: &str, body: &str, idempotent: bool) -> Result<Response> {
+        let headers = [("Authorization", self.token)];
+        let mut attempt = 0;
+        retry::execute_async(
+            &self.config.retry_policy,
+            self.config.retries,
+            idempotent,
+            || {
+                attempt += 1;
+                self.endpoints.send_async(move |base_url| {
+                    let span = trace::request_span(attempt, &base_url);
+                    let url = format!("{base_url}{path}");
+                    trace::send_async(
+                        span,
+                        async move { self.client.post(&url, &headers, body).await },
+                    )
+                })
+            },
+        )
+        .await
+    }
+}
+
+/// Decode the data of a response about `key`, using `not_found` for HTTP 404.
+pub fn decode<T: DeserializeOwned>(
+    response: &Response,
+    key: &str,
+    not_found: impl FnOnce() -> Error,
+) -> Result<T> {
+    if response.status != 200 {
+        return Err(status_error(response, key, not_found));
+    }
+    let api_resp: ApiResponse<T> = serde_json::from_str(&response.body)?;
+    api_resp.into_result_for(key)
+}
+
+/// Check a response about `key` that carries no data.
+pub fn decode_empty(
+    response: &Response,
+    key: &str,
+    not_found: impl FnOnce() -> Error,
+) -> Result<()> {
+    if response.status != 200 {
+        return Err(status_error(response, key, not_found));
+    }
+    let api_resp: ApiResponse<IgnoredAny> = serde_json::from_str(&response.body)?;
+    if api_resp.is_success() {
+        Ok(())
+    } else {
+        Err(Error::from_api(key, api_resp.code, api_resp.message))
+    }
+}
+
+/// Map an unsuccessful response about `key`.
+fn status_error(response: &Response, key: &str, not_found: impl FnOnce() -> Error) -> Error {
+    match response.status {
+        401 => Error::Unauthorized,
+        403 => Error::Forbidden,
+        404 => not_found(),
+        429 => Error::RateLimited(RateLimit::from_response(response)),
+        _ => api_error(response, key),
+    }
+}
+
+/// Map the error code in the body of a response about `key`.
+///
+/// A body that is not an API response is reported with the HTTP status as
+/// code and the body as message.
+pub fn api_error(response: &Response, key: &str) -> Error {
+    let api_resp: ApiResponse<IgnoredAny> =
+        serde_json::from_str(&response.body).unwrap_or_else(|_| ApiResponse {
+            code: response.status.into(),
+            message: response.body.clone(),
+            data: None,
+        });
+    Error::from_api(key, api_resp.code, api_resp.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn not_found() -> Error {
+        Error::ConfigNotFound("order-id".to_string())
+    }
+
+    #[test]
+    fn test_status_codes_are_mapped() {
+        let decoded =
+            |status| decode::<IgnoredAny>(&Response::new(status, String::new()), "k", not_found);
+
+        assert!(matches!(decoded(401), Err(Error::Unauthorized)));
+        assert!(matches!(decoded(403), Err(Error::Forbidden)));
+        assert!(matches!(decoded(404), Err(Error::ConfigNotFound(_))));
+        assert!(matches!(decoded(429), Err(Error::RateLimited(_))));
+        assert!(matches!(decoded(502), Err(Error::Api { code: 502, .. })));
+    }
+
+    #[test]
+    fn test_error_codes_are_mapped_for_any_status() {
+        let body = r#"{"code":40901,"message":"sequence exhausted","data":null}"#;
+
+        let ok = Response::new(200, body.to_string());
+        assert!(matches!(
+            decode::<IgnoredAny>(&ok, "order-id", not_found),
+            Err(Error::SequenceExhausted(key)) if key == "order-id"
+        ));
+
+        let conflict = Response::new(409, body.to_string());
+        assert!(matches!(
+            decode_empty(&conflict, "order-id", not_found),
+            Err(Error::SequenceExhausted(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_empty_accepts_null_data() {
+        let response = Response::new(200, r#"{"code":0,"message":"ok","data":null}"#.to_string());
+        assert!(decode_empty(&response, "order-id", not_found).is_ok());
+    }
+}
@@ -0,0 +1,54 @@
+//! URL encoding helper.
+
+/// Percent-encode a string for use in URL query parameters.
+pub fn encode(input: &str) -> String {
+    let mut result = String::with_capacity(input.len() * 3);
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                result.push(byte as char);
+            }
+            _ => {
+                result.push('%');
+                result.push(
+                    char::from_digit(u32::from(byte >> 4), 16)
+                        .unwrap()
+                        .to_ascii_uppercase(),
+                );
+                result.push(
+                    char::from_digit(u32::from(byte & 0xF), 16)
+                        .unwrap()
+                        .to_ascii_uppercase(),
+                );
+            }
+        }
+    }
+    result
+}
+
+/// Decode a percent-encoded URL query parameter, with `+` as a space.
+///
+/// Invalid escapes are kept as they are.
+#[cfg(feature = "testing")]
+pub fn decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => result.push(b' '),
+            b'%' => {
+                let hex = input.get(i + 1..i + 3);
+                if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    result.push(byte);
+                    i += 3;
+                    continue;
+                }
+                result.push(b'%');
+            }
+            byte => result.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
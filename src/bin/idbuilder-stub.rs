@@ -0,0 +1,78 @@
+//! Local `IDBuilder` stub server for integration tests.
+//!
+//! ```text
+//! idbuilder-stub [--config FILE] [--bind ADDR]
+//! ```
+//!
+//! The server state is read from a TOML or JSON file, see
+//! [`StubConfig`](idbuilder::testing::StubConfig).
+
+use std::process::ExitCode;
+
+use idbuilder::testing::{StubConfig, StubServer};
+
+const USAGE: &str = "\
+Usage: idbuilder-stub [--config FILE] [--bind ADDR]
+
+Options:
+  -c, --config FILE  Server state as TOML, or JSON if FILE ends in .json
+  -b, --bind ADDR    Address to listen on [default: 127.0.0.1:8080]
+  -h, --help         Print this help";
+
+const DEFAULT_BIND: &str = "127.0.0.1:8080";
+
+struct Args {
+    config: Option<String>,
+    bind: String,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        config: None,
+        bind: DEFAULT_BIND.to_string(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{name} needs a value"));
+        match arg.as_str() {
+            "-c" | "--config" => parsed.config = Some(value(&arg)?),
+            "-b" | "--bind" => parsed.bind = value(&arg)?,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unexpected argument '{arg}'")),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let config = match args.config.as_deref().map(StubConfig::from_file) {
+        Some(Ok(config)) => config,
+        Some(Err(err)) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+        None => StubConfig::default(),
+    };
+    let server = match StubServer::bind(args.bind.as_str(), config.into_server()) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("idbuilder-stub listening on {}", server.url());
+    server.wait();
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,122 @@
+//! Connection settings from flags, environment variables and profiles.
+//!
+//! Profiles are [`ClientProfiles`] in a TOML file, by default
+//! `$XDG_CONFIG_HOME/idbuilder/config.toml` or
+//! `~/.config/idbuilder/config.toml`:
+//!
+//! ```toml
+//! [profiles.default]
+//! url = "http://localhost:8080"
+//! key_token = "my-key-token"
+//!
+//! [profiles.staging]
+//! url = "https://idbuilder.staging.example.com"
+//! key_token = "staging-key-token"
+//! admin_token = "staging-admin-token"
+//! timeout = "10s"
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use idbuilder::{ClientProfiles, ClientSettings, Error, Result};
+
+/// Get the default location of the profile file.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("idbuilder").join("config.toml"))
+}
+
+/// Load a profile.
+///
+/// Without an explicit `path`, a missing default file is treated as empty.
+/// Without an explicit `name`, a missing default profile is treated as empty.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidConfig`] if the file cannot be read or parsed, or
+/// if the named profile does not exist.
+pub fn load_profile(path: Option<&Path>, name: Option<&str>) -> Result<ClientSettings> {
+    let (path, explicit_path) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(ClientSettings::default()),
+        },
+    };
+    let input = match std::fs::read_to_string(&path) {
+        Ok(input) => input,
+        Err(err) if explicit_path || name.is_some() => {
+            return Err(Error::InvalidConfig(format!(
+                "cannot read {}: {err}",
+                path.display()
+            )))
+        }
+        Err(_) => return Ok(ClientSettings::default()),
+    };
+    let mut file: ClientProfiles = toml::from_str(&input)
+        .map_err(|err| Error::InvalidConfig(format!("{}: {err}", path.display())))?;
+
+    let profile = file
+        .profiles
+        .remove(name.unwrap_or(ClientProfiles::DEFAULT_PROFILE));
+    match (profile, name) {
+        (Some(profile), _) => Ok(profile),
+        (None, None) => Ok(ClientSettings::default()),
+        (None, Some(name)) => Err(Error::InvalidConfig(format!(
+            "no profile '{name}' in {}",
+            path.display()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn profile_file(name: &str, contents: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("idbuilder-cli-{}-{name}.toml", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_flags_override_profile() {
+        let path = profile_file(
+            "override",
+            r#"
+            [profiles.staging]
+            url = "http://staging:8080"
+            key_token = "staging-token"
+            timeout = "3s"
+            "#,
+        );
+        let profile = load_profile(Some(&path), Some("staging")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let flags = ClientSettings {
+            key_token: Some("flag-token".to_string()),
+            ..ClientSettings::default()
+        };
+        let config = flags.or(profile).into_config().unwrap();
+        assert_eq!(config.base_url, "http://staging:8080");
+        assert_eq!(config.key_token.as_deref(), Some("flag-token"));
+        assert_eq!(config.timeout, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_missing_profile() {
+        let path = profile_file("missing", "[profiles.default]\nurl = \"http://a\"\n");
+        let default = load_profile(Some(&path), None).unwrap();
+        let named = load_profile(Some(&path), Some("prod"));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(default.url.as_deref(), Some("http://a"));
+        assert!(matches!(named, Err(Error::InvalidConfig(message)) if message.contains("prod")));
+    }
+}
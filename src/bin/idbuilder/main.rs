@@ -0,0 +1,589 @@
+//! Command-line tool for generating and inspecting `IDBuilder` IDs.
+//!
+//! ```text
+//! idbuilder increment order-id -n 5
+//! idbuilder formatted invoice-id --format json
+//! idbuilder snowflake config user-id
+//! idbuilder snowflake decompose 5281174511439879 --epoch 1704067200000
+//! idbuilder --profile staging admin increment list --format csv
+//! ```
+//!
+//! Connection settings come from flags, then `IDBUILDER_*` environment
+//! variables, then a profile file, see [`config`].
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use idbuilder::api::KeyAdminApi;
+use idbuilder::http::HttpClient;
+use idbuilder::{
+    ClientSettings, FormattedKeyConfig, IdBuilderClient, IncrementKeyConfig, IssueTokenRequest,
+    KeyConfig, Result, SnowflakeId, SnowflakeKeyConfig, SnowflakeLayout,
+};
+use serde_json::{json, Value};
+
+mod config;
+mod output;
+
+use output::Format;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "idbuilder",
+    version,
+    about = "Generate and inspect IDBuilder IDs"
+)]
+struct Cli {
+    /// Base URL of the server.
+    #[arg(long, global = true, env = "IDBUILDER_URL")]
+    url: Option<String>,
+
+    /// Token for ID generation.
+    #[arg(
+        long,
+        global = true,
+        env = "IDBUILDER_KEY_TOKEN",
+        hide_env_values = true
+    )]
+    key_token: Option<String>,
+
+    /// Token for the admin commands.
+    #[arg(
+        long,
+        global = true,
+        env = "IDBUILDER_ADMIN_TOKEN",
+        hide_env_values = true
+    )]
+    admin_token: Option<String>,
+
+    /// Request timeout, e.g. `10s` or `500ms`.
+    #[arg(
+        long,
+        global = true,
+        env = "IDBUILDER_TIMEOUT",
+        value_name = "DURATION"
+    )]
+    timeout: Option<String>,
+
+    /// Profile to read settings from [default: default].
+    #[arg(long, global = true, env = "IDBUILDER_PROFILE")]
+    profile: Option<String>,
+
+    /// Profile file [default: ~/.config/idbuilder/config.toml].
+    #[arg(long, global = true, env = "IDBUILDER_CONFIG", value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Output format.
+    #[arg(short = 'o', long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate auto-increment IDs.
+    Increment(Generate),
+
+    /// Generate formatted IDs.
+    Formatted(Generate),
+
+    /// Inspect snowflake keys and IDs.
+    #[command(subcommand)]
+    Snowflake(SnowflakeCommand),
+
+    /// Manage keys and key tokens with the admin token.
+    #[command(subcommand)]
+    Admin(AdminCommand),
+}
+
+#[derive(Debug, Args)]
+struct Generate {
+    /// Key to generate IDs for.
+    key: String,
+
+    /// Number of IDs.
+    #[arg(short = 'n', long = "count", default_value_t = 1)]
+    count: u32,
+}
+
+#[derive(Debug, Subcommand)]
+enum SnowflakeCommand {
+    /// Show the layout of a key; the worker ID leased to read it is released.
+    Config {
+        /// Snowflake key.
+        key: String,
+    },
+
+    /// Split IDs into timestamp, worker ID and sequence, without a server.
+    Decompose {
+        /// IDs to decompose.
+        #[arg(required = true)]
+        ids: Vec<SnowflakeId>,
+
+        /// Epoch of the key in milliseconds since the Unix epoch.
+        #[arg(long)]
+        epoch: i64,
+
+        /// Number of worker ID bits.
+        #[arg(
+            long,
+            default_value_t = SnowflakeKeyConfig::DEFAULT_WORKER_BITS,
+            value_parser = bits()
+        )]
+        worker_bits: u8,
+
+        /// Number of sequence bits.
+        #[arg(
+            long,
+            default_value_t = SnowflakeKeyConfig::DEFAULT_SEQUENCE_BITS,
+            value_parser = bits()
+        )]
+        sequence_bits: u8,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AdminCommand {
+    /// Manage auto-increment keys.
+    #[command(subcommand)]
+    Increment(IncrementAdmin),
+
+    /// Manage formatted keys.
+    #[command(subcommand)]
+    Formatted(FormattedAdmin),
+
+    /// Manage snowflake keys.
+    #[command(subcommand)]
+    Snowflake(SnowflakeAdmin),
+
+    /// Manage key tokens.
+    #[command(subcommand)]
+    Token(TokenAdmin),
+}
+
+#[derive(Debug, Subcommand)]
+enum KeyAction {
+    /// List all keys.
+    List,
+
+    /// Show a key.
+    Get {
+        /// Key name.
+        key: String,
+    },
+
+    /// Delete a key.
+    Delete {
+        /// Key name.
+        key: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum IncrementAdmin {
+    /// Create a key.
+    Create {
+        /// Key name.
+        key: String,
+
+        /// First ID.
+        #[arg(long, default_value_t = IncrementKeyConfig::DEFAULT_START)]
+        start: i64,
+
+        /// Difference between consecutive IDs.
+        #[arg(long, default_value_t = IncrementKeyConfig::DEFAULT_STEP)]
+        step: i64,
+
+        /// Largest ID handed out.
+        #[arg(long)]
+        max_value: Option<i64>,
+
+        /// Free-form description.
+        #[arg(long)]
+        description: Option<String>,
+    },
+
+    #[command(flatten)]
+    Key(KeyAction),
+}
+
+#[derive(Debug, Subcommand)]
+enum FormattedAdmin {
+    /// Create a key.
+    Create {
+        /// Key name.
+        key: String,
+
+        /// Pattern such as `INV{yyyyMMdd}-{seq:4}`.
+        #[arg(long)]
+        pattern: String,
+
+        /// Free-form description.
+        #[arg(long)]
+        description: Option<String>,
+    },
+
+    #[command(flatten)]
+    Key(KeyAction),
+}
+
+#[derive(Debug, Subcommand)]
+enum SnowflakeAdmin {
+    /// Create a key.
+    Create {
+        /// Key name.
+        key: String,
+
+        /// Epoch in milliseconds since the Unix epoch.
+        #[arg(long)]
+        epoch: i64,
+
+        /// Number of worker ID bits.
+        #[arg(
+            long,
+            default_value_t = SnowflakeKeyConfig::DEFAULT_WORKER_BITS,
+            value_parser = bits()
+        )]
+        worker_bits: u8,
+
+        /// Number of sequence bits.
+        #[arg(
+            long,
+            default_value_t = SnowflakeKeyConfig::DEFAULT_SEQUENCE_BITS,
+            value_parser = bits()
+        )]
+        sequence_bits: u8,
+
+        /// Free-form description.
+        #[arg(long)]
+        description: Option<String>,
+    },
+
+    #[command(flatten)]
+    Key(KeyAction),
+}
+
+#[derive(Debug, Subcommand)]
+enum TokenAdmin {
+    /// List key tokens.
+    List,
+
+    /// Issue a key token; its secret is only shown here.
+    Issue {
+        /// Keys the token may generate IDs for.
+        #[arg(required = true)]
+        keys: Vec<String>,
+
+        /// Lifetime of the token in seconds.
+        #[arg(long, value_name = "SECS")]
+        ttl: Option<u64>,
+
+        /// Free-form description.
+        #[arg(long)]
+        description: Option<String>,
+    },
+
+    /// Replace the keys a token may generate IDs for.
+    Scope {
+        /// Token ID.
+        id: String,
+
+        /// Keys the token may generate IDs for.
+        #[arg(required = true)]
+        keys: Vec<String>,
+    },
+
+    /// Revoke a token.
+    Revoke {
+        /// Token ID.
+        id: String,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let format = cli.format;
+    match execute(cli) {
+        Ok(value) => {
+            print!("{}", output::render(&value, format));
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parser for a number of snowflake bits; the sum is checked by
+/// [`SnowflakeLayout::try_new`].
+fn bits() -> clap::builder::RangedI64ValueParser<u8> {
+    clap::value_parser!(u8).range(..=i64::from(SnowflakeLayout::MAX_BITS))
+}
+
+/// Run a parsed command line.
+fn execute(cli: Cli) -> Result<Value> {
+    let connect = || {
+        let flags = ClientSettings {
+            url: cli.url,
+            key_token: cli.key_token,
+            admin_token: cli.admin_token,
+            timeout: cli.timeout,
+            ..ClientSettings::default()
+        };
+        let profile = config::load_profile(cli.config.as_deref(), cli.profile.as_deref())?;
+        IdBuilderClient::from_config(flags.or(profile).into_config()?)
+    };
+    run(cli.command, connect)
+}
+
+/// Run a command, connecting with `connect` if it needs a server.
+fn run<C: HttpClient>(
+    command: Command,
+    connect: impl FnOnce() -> Result<IdBuilderClient<C>>,
+) -> Result<Value> {
+    match command {
+        // Decomposing needs no server, so it works without any settings.
+        Command::Snowflake(SnowflakeCommand::Decompose {
+            ids,
+            epoch,
+            worker_bits,
+            sequence_bits,
+        }) => Ok(decompose(
+            &ids,
+            &SnowflakeLayout::try_new(epoch, worker_bits, sequence_bits)?,
+        )),
+        Command::Increment(Generate { key, count }) => {
+            Ok(json!(connect()?.try_increment(key)?.generate(count)?))
+        }
+        Command::Formatted(Generate { key, count }) => {
+            Ok(json!(connect()?.try_formatted(key)?.generate(count)?))
+        }
+        Command::Snowflake(SnowflakeCommand::Config { key }) => {
+            let client = connect()?;
+            let api = client.try_snowflake(key.as_str())?;
+            let config = api.get_config()?;
+            api.release(config.worker_id)?;
+            Ok(json!({
+                "key": key,
+                "epoch": config.epoch,
+                "worker_bits": config.worker_bits,
+                "sequence_bits": config.sequence_bits,
+                "worker_id": config.worker_id,
+            }))
+        }
+        Command::Admin(command) => admin(&connect()?, command),
+    }
+}
+
+fn admin<C: HttpClient>(client: &IdBuilderClient<C>, command: AdminCommand) -> Result<Value> {
+    let admin = client.try_admin()?;
+    match command {
+        AdminCommand::Increment(IncrementAdmin::Create {
+            key,
+            start,
+            step,
+            max_value,
+            description,
+        }) => {
+            let config = IncrementKeyConfig {
+                key,
+                start,
+                step,
+                max_value,
+                description,
+            };
+            Ok(json!(admin.increment().create(&config)?))
+        }
+        AdminCommand::Increment(IncrementAdmin::Key(action)) => {
+            key_action(&admin.increment(), action)
+        }
+        AdminCommand::Formatted(FormattedAdmin::Create {
+            key,
+            pattern,
+            description,
+        }) => {
+            let config = FormattedKeyConfig {
+                key,
+                pattern,
+                description,
+            };
+            Ok(json!(admin.formatted().create(&config)?))
+        }
+        AdminCommand::Formatted(FormattedAdmin::Key(action)) => {
+            key_action(&admin.formatted(), action)
+        }
+        AdminCommand::Snowflake(SnowflakeAdmin::Create {
+            key,
+            epoch,
+            worker_bits,
+            sequence_bits,
+            description,
+        }) => {
+            SnowflakeLayout::try_new(epoch, worker_bits, sequence_bits)?;
+            let config = SnowflakeKeyConfig {
+                key,
+                epoch,
+                worker_bits,
+                sequence_bits,
+                description,
+            };
+            Ok(json!(admin.snowflake().create(&config)?))
+        }
+        AdminCommand::Snowflake(SnowflakeAdmin::Key(action)) => {
+            key_action(&admin.snowflake(), action)
+        }
+        AdminCommand::Token(TokenAdmin::List) => Ok(json!(admin.tokens().list()?)),
+        AdminCommand::Token(TokenAdmin::Issue {
+            keys,
+            ttl,
+            description,
+        }) => {
+            let mut request = IssueTokenRequest::new(keys);
+            if let Some(ttl) = ttl {
+                request = request.with_ttl(std::time::Duration::from_secs(ttl));
+            }
+            if let Some(description) = description {
+                request = request.with_description(description);
+            }
+            Ok(json!(admin.tokens().issue(&request)?))
+        }
+        AdminCommand::Token(TokenAdmin::Scope { id, keys }) => {
+            Ok(json!(admin.tokens().scope(&id, &keys)?))
+        }
+        AdminCommand::Token(TokenAdmin::Revoke { id }) => {
+            admin.tokens().revoke(&id)?;
+            Ok(json!({ "id": id, "revoked": true }))
+        }
+    }
+}
+
+fn key_action<C: HttpClient, K: KeyConfig>(
+    api: &KeyAdminApi<'_, C, K>,
+    action: KeyAction,
+) -> Result<Value> {
+    match action {
+        KeyAction::List => Ok(json!(api.list()?)),
+        KeyAction::Get { key } => Ok(json!(api.get(&key)?)),
+        KeyAction::Delete { key } => {
+            api.delete(&key)?;
+            Ok(json!({ "key": key, "deleted": true }))
+        }
+    }
+}
+
+fn decompose(ids: &[SnowflakeId], layout: &SnowflakeLayout) -> Value {
+    ids.iter()
+        .map(|id| {
+            let parts = id.parts(layout);
+            json!({
+                "id": id,
+                "time": format_utc(parts.timestamp_millis),
+                "timestamp_millis": parts.timestamp_millis,
+                "worker_id": parts.worker_id,
+                "sequence": parts.sequence,
+            })
+        })
+        .collect()
+}
+
+/// Format milliseconds since the Unix epoch as an RFC 3339 UTC time.
+fn format_utc(millis: i64) -> String {
+    let secs = millis.div_euclid(1000);
+    let (days, time) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // Days to civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        millis.rem_euclid(1000)
+    )
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use idbuilder::testing::FakeServer;
+    use idbuilder::{ClientConfig, Error};
+
+    use super::*;
+
+    fn run_args(server: &FakeServer, args: &[&str]) -> Result<Value> {
+        let cli =
+            Cli::try_parse_from(std::iter::once("idbuilder").chain(args.iter().copied())).unwrap();
+        let config = ClientConfig::new("http://idbuilder.test")
+            .with_key_token("test-token")
+            .with_admin_token("admin-token");
+        run(cli.command, || {
+            Ok(IdBuilderClient::with_http_client(config, server.clone()))
+        })
+    }
+
+    #[test]
+    fn test_commands() {
+        let server = FakeServer::new()
+            .with_key_token("test-token")
+            .with_admin_token("admin-token");
+
+        let created = run_args(
+            &server,
+            &["admin", "increment", "create", "order-id", "--start", "100"],
+        )
+        .unwrap();
+        assert_eq!(created["start"], 100);
+        assert_eq!(
+            run_args(&server, &["increment", "order-id", "-n", "2"]).unwrap(),
+            json!([100, 101])
+        );
+        assert_eq!(
+            run_args(&server, &["admin", "increment", "list"]).unwrap()[0]["key"],
+            "order-id"
+        );
+
+        run_args(
+            &server,
+            &["admin", "snowflake", "create", "user-id", "--epoch", "0"],
+        )
+        .unwrap();
+        let config = run_args(&server, &["snowflake", "config", "user-id"]).unwrap();
+        assert_eq!(config["worker_bits"], 10);
+        assert!(server.leased_workers("user-id").is_empty());
+    }
+
+    #[test]
+    fn test_decompose_rejects_too_many_bits() {
+        let parse = |args: &[&str]| {
+            let base = ["idbuilder", "snowflake", "decompose", "1", "--epoch", "0"];
+            Cli::try_parse_from(base.iter().chain(args))
+        };
+
+        assert!(parse(&["--worker-bits", "64"]).is_err());
+        let cli = parse(&["--worker-bits", "40", "--sequence-bits", "30"]).unwrap();
+        assert!(matches!(execute(cli), Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_decompose() {
+        let layout = SnowflakeLayout::new(1_704_067_200_000, 10, 12);
+        let id = layout.compose(1_705_326_330_123, 5, 7);
+
+        let parts = decompose(&[id], &layout);
+        assert_eq!(parts[0]["time"], "2024-01-15T13:45:30.123Z");
+        assert_eq!(parts[0]["worker_id"], 5);
+        assert_eq!(parts[0]["sequence"], 7);
+    }
+}
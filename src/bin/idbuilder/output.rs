@@ -0,0 +1,190 @@
+//! Rendering of command results as text, JSON or CSV.
+
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+/// Output format of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Plain text: one ID per line, `field value` lines or a table.
+    Text,
+
+    /// Pretty-printed JSON.
+    Json,
+
+    /// CSV with a header row.
+    Csv,
+}
+
+/// Render a command result.
+///
+/// Results are lists of IDs, single records or lists of records. A list of
+/// IDs is written to CSV as an `id` column.
+pub fn render(value: &Value, format: Format) -> String {
+    match format {
+        Format::Text => text(value),
+        Format::Json => serde_json::to_string_pretty(value).unwrap_or_default(),
+        Format::Csv => csv(value),
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            let (columns, rows) = table(items);
+            let header = columns.iter().map(|column| column.to_uppercase()).collect();
+            aligned(std::iter::once(header).chain(rows))
+        }
+        Value::Array(items) => items.iter().map(|item| cell(item) + "\n").collect(),
+        Value::Object(record) => aligned(
+            record
+                .iter()
+                .map(|(field, value)| vec![field.clone(), cell(value)]),
+        ),
+        value => cell(value) + "\n",
+    }
+}
+
+fn csv(value: &Value) -> String {
+    let (columns, rows) = match value {
+        Value::Array(items) if items.iter().all(Value::is_object) => table(items),
+        Value::Array(items) => (
+            vec!["id".to_string()],
+            items.iter().map(|item| vec![cell(item)]).collect(),
+        ),
+        Value::Object(_) => table(std::slice::from_ref(value)),
+        value => (vec!["value".to_string()], vec![vec![cell(value)]]),
+    };
+
+    let mut output = String::new();
+    for row in std::iter::once(columns).chain(rows) {
+        let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        let _ = writeln!(output, "{}", fields.join(","));
+    }
+    output
+}
+
+/// Collect the columns of `records`, in the order first seen, and their rows.
+fn table(records: &[Value]) -> (Vec<String>, Vec<Vec<String>>) {
+    let empty = Map::new();
+    let records: Vec<&Map<String, Value>> = records
+        .iter()
+        .map(|record| record.as_object().unwrap_or(&empty))
+        .collect();
+
+    let mut columns: Vec<String> = Vec::new();
+    for field in records.iter().flat_map(|record| record.keys()) {
+        if !columns.contains(field) {
+            columns.push(field.clone());
+        }
+    }
+    let rows = records
+        .iter()
+        .map(|record| {
+            columns
+                .iter()
+                .map(|column| record.get(column).map(cell).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    (columns, rows)
+}
+
+/// Write rows with each column padded to its widest cell.
+fn aligned(rows: impl IntoIterator<Item = Vec<String>>) -> String {
+    let rows: Vec<Vec<String>> = rows.into_iter().collect();
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &rows {
+        for (index, field) in row.iter().enumerate() {
+            let width = field.chars().count();
+            match widths.get_mut(index) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    let mut output = String::new();
+    for row in &rows {
+        let mut line = String::new();
+        for (field, width) in row.iter().zip(&widths) {
+            let _ = write!(line, "{field:width$}  ");
+        }
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+/// Format a value as a single cell.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) if items.iter().all(Value::is_string) => {
+            items.iter().map(cell).collect::<Vec<_>>().join(";")
+        }
+        value => value.to_string(),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_ids() {
+        let ids = json!([1000, 1001]);
+
+        assert_eq!(render(&ids, Format::Text), "1000\n1001\n");
+        assert_eq!(render(&ids, Format::Csv), "id\n1000\n1001\n");
+        assert_eq!(render(&ids, Format::Json), "[\n  1000,\n  1001\n]");
+    }
+
+    #[test]
+    fn test_records() {
+        let keys = json!([
+            {"key": "order-id", "start": 1, "step": 1},
+            {"key": "invoice, 2024", "start": 100, "step": 10, "description": "say \"hi\""},
+        ]);
+
+        assert_eq!(
+            render(&keys, Format::Text),
+            "KEY            START  STEP  DESCRIPTION\n\
+             order-id       1      1\n\
+             invoice, 2024  100    10    say \"hi\"\n"
+        );
+        assert_eq!(
+            render(&keys, Format::Csv),
+            "key,start,step,description\n\
+             order-id,1,1,\n\
+             \"invoice, 2024\",100,10,\"say \"\"hi\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_single_record() {
+        let token = json!({"id": "tok-1", "allowed_keys": ["a", "b"], "expires_at": null});
+
+        assert_eq!(
+            render(&token, Format::Text),
+            "id            tok-1\nallowed_keys  a;b\nexpires_at\n"
+        );
+        assert_eq!(
+            render(&token, Format::Csv),
+            "id,allowed_keys,expires_at\ntok-1,a;b,\n"
+        );
+    }
+}
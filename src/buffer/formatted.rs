@@ -0,0 +1,450 @@
+//! Prefetching pool of formatted IDs.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{backoff_after, non_empty, pause_after, paused_error, record_refill, remaining_pause};
+use crate::http::HttpClient;
+use crate::{Error, IdBuilderClient, Result};
+
+/// Maximum number of IDs the server returns per request.
+const MAX_BATCH_SIZE: u32 = 1000;
+
+/// Options controlling refills of a [`FormattedIdPool`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolOptions {
+    /// Number of IDs requested per refill (capped at 1000).
+    pub batch_size: u32,
+
+    /// Number of queued IDs at or below which a background refill starts.
+    pub low_watermark: usize,
+
+    /// How long [`take`](FormattedIdPool::take) waits for a drained queue.
+    pub wait_timeout: Duration,
+}
+
+impl PoolOptions {
+    /// Default number of IDs per refill.
+    pub const DEFAULT_BATCH_SIZE: u32 = 100;
+
+    /// Default refill watermark.
+    pub const DEFAULT_LOW_WATERMARK: usize = 20;
+
+    /// Default wait timeout (5 seconds).
+    pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Create options with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+            low_watermark: Self::DEFAULT_LOW_WATERMARK,
+            wait_timeout: Self::DEFAULT_WAIT_TIMEOUT,
+        }
+    }
+
+    /// Set the number of IDs requested per refill.
+    #[must_use]
+    pub const fn with_batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Set the refill watermark.
+    #[must_use]
+    pub const fn with_low_watermark(mut self, low_watermark: usize) -> Self {
+        self.low_watermark = low_watermark;
+        self
+    }
+
+    /// Set how long to wait for IDs when a queue is drained.
+    #[must_use]
+    pub const fn with_wait_timeout(mut self, timeout: Duration) -> Self {
+        self.wait_timeout = timeout;
+        self
+    }
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Thread-safe pool of pre-generated formatted IDs.
+///
+/// Keeps a local queue per key and refills it on a background thread in
+/// batches whenever it falls to the low watermark. Callers take IDs from the
+/// queue and only wait when it is drained.
+///
+/// IDs still queued when the pool is dropped are never handed out.
+///
+/// # Example
+///
+/// ```no_run
+/// use idbuilder::{FormattedIdPool, IdBuilderClient, PoolOptions, Result};
+///
+/// fn main() -> Result<()> {
+///     let client = IdBuilderClient::new("http://localhost:8080", "my-key-token")?;
+///     let pool = FormattedIdPool::new(client, PoolOptions::new());
+///
+///     let invoice = pool.take("invoice-id")?;
+///     let batch = pool.take_many("invoice-id", 10)?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FormattedIdPool<C> {
+    inner: Arc<Inner<C>>,
+}
+
+impl<C> Clone for FormattedIdPool<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner<C> {
+    client: IdBuilderClient<C>,
+    options: PoolOptions,
+    queues: Mutex<HashMap<String, Queue>>,
+    refilled: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    ids: VecDeque<String>,
+    refilling: bool,
+
+    /// Number of callers waiting for the running refill.
+    waiters: usize,
+
+    /// Error of a failed refill, kept only for a waiting caller.
+    error: Option<Error>,
+
+    /// Refills are paused until then after the server rate limited one.
+    paused_until: Option<Instant>,
+
+    /// Low watermark refills wait until then after one failed.
+    prefetch_after: Option<Instant>,
+}
+
+impl<C: HttpClient + Send + Sync + 'static> FormattedIdPool<C> {
+    /// Create an empty pool.
+    ///
+    /// Queues are created lazily on the first request for a key.
+    #[must_use]
+    pub fn new(client: IdBuilderClient<C>, options: PoolOptions) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                options,
+                queues: Mutex::new(HashMap::new()),
+                refilled: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Take a single ID for the given key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PoolTimeout`] if no ID became available within the
+    /// configured wait timeout, or the refill error if fetching failed.
+    pub fn take(&self, key: &str) -> Result<String> {
+        let mut ids = self.take_many(key, 1)?;
+        ids.pop().ok_or_else(|| Error::PoolTimeout(key.to_string()))
+    }
+
+    /// Take `count` IDs for the given key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PoolTimeout`] if not enough IDs became available within
+    /// the configured wait timeout, or the error of a refill it waited for.
+    /// After the server rate limited a refill, returns [`Error::RateLimited`]
+    /// without a request until the requested delay has passed.
+    pub fn take_many(&self, key: &str, count: usize) -> Result<Vec<String>> {
+        let deadline = Instant::now() + self.inner.options.wait_timeout;
+        let mut queues = self.inner.lock();
+        loop {
+            let queue = queues.entry(key.to_string()).or_default();
+
+            if queue.ids.len() >= count {
+                let ids = queue.ids.drain(..count).collect();
+                if queue.ids.len() <= self.inner.options.low_watermark {
+                    self.start_refill(key, queue, 0);
+                }
+                return Ok(ids);
+            }
+
+            if let Some(err) = queue.error.take() {
+                return Err(err);
+            }
+
+            if let Some(remaining) = remaining_pause(&mut queue.paused_until) {
+                return Err(paused_error(remaining));
+            }
+
+            let missing = count - queue.ids.len();
+            self.start_refill(key, queue, missing);
+
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                return Err(Error::PoolTimeout(key.to_string()));
+            }
+            queue.waiters += 1;
+            queues = self
+                .inner
+                .refilled
+                .wait_timeout(queues, timeout)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+            queues.entry(key.to_string()).or_default().waiters -= 1;
+        }
+    }
+
+    /// Number of IDs currently queued for the given key.
+    #[must_use]
+    pub fn available(&self, key: &str) -> usize {
+        self.inner
+            .lock()
+            .get(key)
+            .map_or(0, |queue| queue.ids.len())
+    }
+
+    /// Start a background refill for `key` unless one is already running or
+    /// refills are paused.
+    ///
+    /// The batch is sized to cover `missing` IDs, within the server limit. A
+    /// low watermark refill, with nothing missing, is skipped while backing
+    /// off from a failed one.
+    fn start_refill(&self, key: &str, queue: &mut Queue, missing: usize) {
+        if queue.refilling
+            || remaining_pause(&mut queue.paused_until).is_some()
+            || (missing == 0 && remaining_pause(&mut queue.prefetch_after).is_some())
+        {
+            return;
+        }
+        queue.refilling = true;
+
+        let wanted = u32::try_from(missing).unwrap_or(u32::MAX);
+        let size = self
+            .inner
+            .options
+            .batch_size
+            .max(wanted)
+            .clamp(1, MAX_BATCH_SIZE);
+        let inner = Arc::clone(&self.inner);
+        let key = key.to_string();
+        thread::spawn(move || {
+            let result = inner
+                .client
+                .try_formatted(key.as_str())
+                .and_then(|api| api.generate(size));
+            let result = non_empty(&key, result);
+            inner.finish_refill(key, result);
+            inner.refilled.notify_all();
+        });
+    }
+}
+
+impl<C> Inner<C> {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Queue>> {
+        self.queues.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn finish_refill(&self, key: String, result: Result<Vec<String>>) {
+        record_refill(self.client.metrics(), &key, &result);
+        let mut queues = self.lock();
+        let queue = queues.entry(key).or_default();
+        queue.refilling = false;
+        queue.prefetch_after = backoff_after(&result);
+        match result {
+            Ok(ids) => queue.ids.extend(ids),
+            Err(err) => {
+                queue.paused_until = pause_after(&err);
+                // Without a waiting caller the error is dropped, so that a
+                // later caller gets a fresh attempt instead of a stale error.
+                if queue.waiters > 0 {
+                    queue.error = Some(err);
+                }
+            }
+        }
+        drop(queues);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Response;
+    use crate::ClientConfig;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Default)]
+    struct InvoiceHttpClient {
+        next: AtomicU32,
+        requests: AtomicU32,
+        fail: bool,
+        fail_request: Option<u32>,
+        rate_limited: bool,
+    }
+
+    impl HttpClient for InvoiceHttpClient {
+        fn get(&self, url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+            let request = self.requests.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.fail_request == Some(request) {
+                return Ok(Response::new(500, String::new()));
+            }
+            if self.fail {
+                return Ok(Response::new(404, String::new()));
+            }
+            if self.rate_limited {
+                return Ok(Response::new(429, String::new()).with_header("Retry-After", "60"));
+            }
+            let size: u32 = url.rsplit("size=").next().unwrap().parse().unwrap();
+            let start = self.next.fetch_add(size, Ordering::SeqCst) + 1;
+            let ids: Vec<String> = (start..start + size)
+                .map(|n| format!("INV-{n:04}"))
+                .collect();
+            let body = format!(
+                r#"{{"code":0,"message":"success","data":{{"ids":{}}}}}"#,
+                serde_json::to_string(&ids).unwrap()
+            );
+            Ok(Response::new(200, body))
+        }
+
+        fn post(&self, _url: &str, _headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+            unreachable!()
+        }
+    }
+
+    fn pool(http: InvoiceHttpClient, options: PoolOptions) -> FormattedIdPool<InvoiceHttpClient> {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        let client = IdBuilderClient::with_http_client(config, http);
+        FormattedIdPool::new(client, options)
+    }
+
+    #[test]
+    fn test_take_in_order() {
+        let pool = pool(
+            InvoiceHttpClient::default(),
+            PoolOptions::new().with_batch_size(5).with_low_watermark(0),
+        );
+
+        assert_eq!(pool.take("invoice-id").unwrap(), "INV-0001");
+        assert_eq!(
+            pool.take_many("invoice-id", 3).unwrap(),
+            vec!["INV-0002", "INV-0003", "INV-0004"]
+        );
+        assert_eq!(pool.available("invoice-id"), 1);
+    }
+
+    #[test]
+    fn test_take_many_spans_batches() {
+        let pool = pool(
+            InvoiceHttpClient::default(),
+            PoolOptions::new().with_batch_size(10),
+        );
+
+        let ids = pool.take_many("invoice-id", 2500).unwrap();
+        assert_eq!(ids.len(), 2500);
+        assert_eq!(ids[2499], "INV-2500");
+    }
+
+    #[test]
+    fn test_refill_error_is_returned() {
+        let http = InvoiceHttpClient {
+            fail: true,
+            ..InvoiceHttpClient::default()
+        };
+        let pool = pool(http, PoolOptions::new());
+
+        assert!(matches!(
+            pool.take("invoice-id"),
+            Err(Error::ConfigNotFound(key)) if key == "invoice-id"
+        ));
+    }
+
+    #[test]
+    fn test_background_refill_error_is_not_kept() {
+        let http = InvoiceHttpClient {
+            fail_request: Some(2),
+            ..InvoiceHttpClient::default()
+        };
+        let pool = pool(
+            http,
+            PoolOptions::new().with_batch_size(5).with_low_watermark(4),
+        );
+        let requests = || {
+            pool.inner
+                .client
+                .http_client()
+                .requests
+                .load(Ordering::SeqCst)
+        };
+
+        // Taking the first ID starts a low watermark refill, which fails.
+        assert_eq!(pool.take("invoice-id").unwrap(), "INV-0001");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while (requests() < 2 || pool.inner.lock()["invoice-id"].refilling)
+            && Instant::now() < deadline
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // The failed refill backs off instead of starting another one.
+        assert_eq!(pool.take_many("invoice-id", 4).unwrap().len(), 4);
+        assert_eq!(requests(), 2);
+
+        // A caller that runs short gets a fresh refill, not the old error.
+        assert_eq!(pool.take("invoice-id").unwrap(), "INV-0006");
+        assert_eq!(requests(), 3);
+    }
+
+    #[test]
+    fn test_keys_have_separate_queues() {
+        let pool = pool(
+            InvoiceHttpClient::default(),
+            PoolOptions::new().with_batch_size(5).with_low_watermark(0),
+        );
+
+        pool.take("invoice-id").unwrap();
+        pool.take("receipt-id").unwrap();
+        assert_eq!(pool.available("invoice-id"), 4);
+        assert_eq!(pool.available("receipt-id"), 4);
+        assert_eq!(pool.available("unknown-id"), 0);
+    }
+
+    #[test]
+    fn test_rate_limit_pauses_refills() {
+        let http = InvoiceHttpClient {
+            rate_limited: true,
+            ..InvoiceHttpClient::default()
+        };
+        let pool = pool(http, PoolOptions::new());
+
+        let err = pool.take("invoice-id").unwrap_err();
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(60)));
+
+        assert!(matches!(
+            pool.take("invoice-id"),
+            Err(Error::RateLimited(_))
+        ));
+        assert_eq!(
+            pool.inner
+                .client
+                .http_client()
+                .requests
+                .load(Ordering::SeqCst),
+            1
+        );
+    }
+}
@@ -0,0 +1,578 @@
+//! Double-buffered segment cache for auto-increment IDs.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{backoff_after, non_empty, pause_after, paused_error, record_refill, remaining_pause};
+use crate::http::HttpClient;
+use crate::{IdBuilderClient, Result};
+
+/// Options controlling segment size and prefetching of a [`BufferedIncrement`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferOptions {
+    /// Size of the first segment fetched from the server.
+    pub initial_size: u32,
+
+    /// Lower bound for adaptive segment sizing.
+    pub min_size: u32,
+
+    /// Upper bound for adaptive segment sizing (the server caps batches at 1000).
+    pub max_size: u32,
+
+    /// Fraction of the current segment left when the next one is prefetched.
+    pub watermark: f64,
+
+    /// Desired time for a segment to be consumed.
+    ///
+    /// Segment sizes are adjusted so that, at the observed consumption rate,
+    /// each segment lasts roughly this long.
+    pub target_duration: Duration,
+}
+
+impl BufferOptions {
+    /// Default size of the first segment.
+    pub const DEFAULT_INITIAL_SIZE: u32 = 100;
+
+    /// Default lower bound for the segment size.
+    pub const DEFAULT_MIN_SIZE: u32 = 10;
+
+    /// Default upper bound for the segment size.
+    pub const DEFAULT_MAX_SIZE: u32 = 1000;
+
+    /// Default prefetch watermark (20% of the segment left).
+    pub const DEFAULT_WATERMARK: f64 = 0.2;
+
+    /// Default target segment lifetime (30 seconds).
+    pub const DEFAULT_TARGET_DURATION: Duration = Duration::from_secs(30);
+
+    /// Create options with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            initial_size: Self::DEFAULT_INITIAL_SIZE,
+            min_size: Self::DEFAULT_MIN_SIZE,
+            max_size: Self::DEFAULT_MAX_SIZE,
+            watermark: Self::DEFAULT_WATERMARK,
+            target_duration: Self::DEFAULT_TARGET_DURATION,
+        }
+    }
+
+    /// Set the size of the first segment.
+    #[must_use]
+    pub const fn with_initial_size(mut self, size: u32) -> Self {
+        self.initial_size = size;
+        self
+    }
+
+    /// Set the bounds for adaptive segment sizing.
+    #[must_use]
+    pub const fn with_size_range(mut self, min: u32, max: u32) -> Self {
+        self.min_size = min;
+        self.max_size = max;
+        self
+    }
+
+    /// Set the prefetch watermark as a fraction of the segment size.
+    #[must_use]
+    pub const fn with_watermark(mut self, watermark: f64) -> Self {
+        self.watermark = watermark;
+        self
+    }
+
+    /// Set the desired lifetime of a segment.
+    #[must_use]
+    pub const fn with_target_duration(mut self, duration: Duration) -> Self {
+        self.target_duration = duration;
+        self
+    }
+
+    fn clamp_size(&self, size: u32) -> u32 {
+        size.clamp(self.min_size.max(1), self.max_size.max(1))
+    }
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Locally buffered auto-increment ID generator.
+///
+/// Fetches segments of IDs from the server and hands them out with
+/// [`next_id`](Self::next_id) without a network round trip. Two segments are
+/// kept: when the current one falls below the configured watermark, the next
+/// one is fetched on a background thread so that callers rarely wait.
+///
+/// Segment sizes adapt to the consumption rate so that each segment lasts
+/// about [`BufferOptions::target_duration`].
+///
+/// IDs left in the buffer when it is dropped are never handed out, so a
+/// buffered key will show gaps after restarts.
+///
+/// # Example
+///
+/// ```no_run
+/// use idbuilder::{BufferOptions, BufferedIncrement, IdBuilderClient, Result};
+///
+/// fn main() -> Result<()> {
+///     let client = IdBuilderClient::new("http://localhost:8080", "my-key-token")?;
+///     let orders = BufferedIncrement::new(client, "order-id", BufferOptions::new());
+///
+///     let id = orders.next_id()?;
+///     println!("Order ID: {}", id);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BufferedIncrement<C> {
+    inner: Arc<Inner<C>>,
+}
+
+impl<C> Clone for BufferedIncrement<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner<C> {
+    client: IdBuilderClient<C>,
+    key: String,
+    options: BufferOptions,
+    state: Mutex<State>,
+    loaded: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Segment IDs are currently handed out from.
+    current: VecDeque<i64>,
+
+    /// Prefetched segment, swapped in once `current` is drained.
+    next: Option<VecDeque<i64>>,
+
+    /// Whether a fetch is in flight.
+    loading: bool,
+
+    /// Size of the next segment to request.
+    segment_size: u32,
+
+    /// Size of the current segment when it was swapped in.
+    current_size: u32,
+
+    /// When the current segment was swapped in.
+    current_since: Option<Instant>,
+
+    /// Fetches are paused until then after the server rate limited one.
+    paused_until: Option<Instant>,
+
+    /// Background prefetches wait until then after one failed.
+    prefetch_after: Option<Instant>,
+}
+
+impl<C: HttpClient + Send + Sync + 'static> BufferedIncrement<C> {
+    /// Create a buffered generator for the given key.
+    ///
+    /// No request is made until the first ID is requested.
+    #[must_use]
+    pub fn new(client: IdBuilderClient<C>, key: impl Into<String>, options: BufferOptions) -> Self {
+        let segment_size = options.clamp_size(options.initial_size);
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                key: key.into(),
+                options,
+                state: Mutex::new(State {
+                    current: VecDeque::new(),
+                    next: None,
+                    loading: false,
+                    segment_size,
+                    current_size: 0,
+                    current_since: None,
+                    paused_until: None,
+                    prefetch_after: None,
+                }),
+                loaded: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Get the next ID from the buffer.
+    ///
+    /// Blocks only if both segments are empty and a fetch is required.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a segment has to be fetched synchronously and the
+    /// request fails, or [`Error::SequenceExhausted`](crate::Error::SequenceExhausted)
+    /// if the server returns no IDs. After the server rate limited a fetch, returns
+    /// [`Error::RateLimited`](crate::Error::RateLimited) without a request
+    /// until the requested delay has passed.
+    #[allow(clippy::significant_drop_tightening)]
+    pub fn next_id(&self) -> Result<i64> {
+        let mut state = self.inner.lock();
+        loop {
+            if let Some(id) = state.current.pop_front() {
+                self.maybe_prefetch(&mut state);
+                return Ok(id);
+            }
+
+            if let Some(next) = state.next.take() {
+                self.inner.swap_in(&mut state, next);
+                continue;
+            }
+
+            if state.loading {
+                state = self
+                    .inner
+                    .loaded
+                    .wait(state)
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                continue;
+            }
+
+            if let Some(remaining) = remaining_pause(&mut state.paused_until) {
+                return Err(paused_error(remaining));
+            }
+
+            // Nothing buffered and nothing in flight: fetch in the foreground.
+            state.loading = true;
+            let size = state.segment_size;
+            drop(state);
+            let result = self.inner.fetch(size);
+            state = self.inner.lock();
+            state.loading = false;
+            state.paused_until = result.as_ref().err().and_then(pause_after);
+            self.inner.loaded.notify_all();
+
+            let ids = result?;
+            self.inner.swap_in(&mut state, ids.into());
+        }
+    }
+
+    /// Number of IDs currently buffered locally, across both segments.
+    #[must_use]
+    pub fn buffered(&self) -> usize {
+        let state = self.inner.lock();
+        state.current.len() + state.next.as_ref().map_or(0, VecDeque::len)
+    }
+
+    /// Get the key this buffer generates IDs for.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.inner.key
+    }
+
+    fn maybe_prefetch(&self, state: &mut MutexGuard<'_, State>) {
+        if state.loading
+            || state.next.is_some()
+            || remaining_pause(&mut state.paused_until).is_some()
+            || remaining_pause(&mut state.prefetch_after).is_some()
+        {
+            return;
+        }
+
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let watermark = (f64::from(state.current_size) * self.inner.options.watermark) as usize;
+        if state.current.len() > watermark {
+            return;
+        }
+
+        state.loading = true;
+        let size = state.segment_size;
+        let inner = Arc::clone(&self.inner);
+        thread::spawn(move || {
+            let result = inner.fetch(size);
+            {
+                let mut state = inner.lock();
+                state.loading = false;
+                state.paused_until = result.as_ref().err().and_then(pause_after);
+                state.prefetch_after = backoff_after(&result);
+                // A failed prefetch is not reported here; the next foreground
+                // fetch will surface the error if it persists.
+                if let Ok(ids) = result {
+                    state.next = Some(ids.into());
+                }
+            }
+            inner.loaded.notify_all();
+        });
+    }
+}
+
+impl<C: HttpClient> Inner<C> {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn fetch(&self, size: u32) -> Result<Vec<i64>> {
+        let result = self
+            .client
+            .try_increment(self.key.as_str())
+            .and_then(|api| api.generate(size));
+        let result = non_empty(&self.key, result);
+        record_refill(self.client.metrics(), &self.key, &result);
+        result
+    }
+
+    /// Make `segment` current and resize future segments from the
+    /// consumption rate of the one it replaces.
+    fn swap_in(&self, state: &mut State, segment: VecDeque<i64>) {
+        let now = Instant::now();
+        if let Some(since) = state.current_since {
+            state.segment_size =
+                self.adapt_size(state.current_size, now.saturating_duration_since(since));
+        }
+        state.current_size = u32::try_from(segment.len()).unwrap_or(u32::MAX);
+        state.current_since = Some(now);
+        state.current = segment;
+    }
+
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn adapt_size(&self, consumed: u32, elapsed: Duration) -> u32 {
+        let elapsed = elapsed.as_secs_f64().max(0.001);
+        let rate = f64::from(consumed) / elapsed;
+        let wanted = rate * self.options.target_duration.as_secs_f64();
+        self.options
+            .clamp_size(wanted.min(f64::from(u32::MAX)) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Response;
+    use crate::ClientConfig;
+    use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingHttpClient {
+        next: AtomicI64,
+        requests: AtomicUsize,
+        failing: AtomicBool,
+    }
+
+    impl HttpClient for CountingHttpClient {
+        fn get(&self, url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Ok(Response::new(500, String::new()));
+            }
+            let size: i64 = url.rsplit("size=").next().unwrap().parse().unwrap();
+            let start = self.next.fetch_add(size, Ordering::SeqCst) + 1;
+            let ids: Vec<i64> = (start..start + size).collect();
+            let body = format!(r#"{{"code":0,"message":"success","data":{{"ids":{ids:?}}}}}"#);
+            Ok(Response::new(200, body))
+        }
+
+        fn post(&self, _url: &str, _headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+            unreachable!()
+        }
+    }
+
+    fn client() -> IdBuilderClient<CountingHttpClient> {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        IdBuilderClient::with_http_client(config, CountingHttpClient::default())
+    }
+
+    #[test]
+    fn test_ids_are_sequential_across_segments() {
+        let options = BufferOptions::new()
+            .with_initial_size(10)
+            .with_size_range(10, 10);
+        let buffer = BufferedIncrement::new(client(), "order-id", options);
+
+        let ids: Vec<i64> = (0..35).map(|_| buffer.next_id().unwrap()).collect();
+        assert_eq!(ids, (1..=35).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_prefetches_below_watermark() {
+        let client = client();
+        let options = BufferOptions::new()
+            .with_initial_size(10)
+            .with_size_range(10, 10)
+            .with_watermark(0.5);
+        let buffer = BufferedIncrement::new(client.clone(), "order-id", options);
+
+        for _ in 0..6 {
+            buffer.next_id().unwrap();
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while buffer.buffered() < 14 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(buffer.buffered(), 14);
+        assert_eq!(client.http_client().requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_failed_prefetch_backs_off() {
+        let client = client();
+        let options = BufferOptions::new()
+            .with_initial_size(10)
+            .with_size_range(10, 10)
+            .with_watermark(0.5);
+        let buffer = BufferedIncrement::new(client.clone(), "order-id", options);
+
+        buffer.next_id().unwrap();
+        client.http_client().failing.store(true, Ordering::SeqCst);
+        for _ in 0..9 {
+            buffer.next_id().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        // The first segment and a single failed prefetch.
+        assert_eq!(client.http_client().requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_refills_are_reported_to_metrics() {
+        use crate::{Counter, Histogram, MetricsRecorder};
+
+        let recorder = MetricsRecorder::new();
+        let config = ClientConfig::new("http://localhost:8080")
+            .with_key_token("test-token")
+            .with_metrics(recorder.clone());
+        let client = IdBuilderClient::with_http_client(config, CountingHttpClient::default());
+        let options = BufferOptions::new()
+            .with_initial_size(10)
+            .with_size_range(10, 10);
+        let buffer = BufferedIncrement::new(client, "order-id", options);
+
+        for _ in 0..5 {
+            buffer.next_id().unwrap();
+        }
+
+        let snapshot = recorder.snapshot();
+        assert_eq!(snapshot.counter(Counter::BufferRefills, "order-id"), 1);
+        assert_eq!(snapshot.counter(Counter::IdsIssued, "order-id"), 10);
+        let sizes = snapshot
+            .histogram(Histogram::RefillSize, "order-id")
+            .unwrap();
+        assert!((sizes.sum - 10.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_concurrent_ids_are_unique() {
+        let options = BufferOptions::new().with_initial_size(50);
+        let buffer = BufferedIncrement::new(client(), "order-id", options);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let buffer = buffer.clone();
+                thread::spawn(move || {
+                    (0..500)
+                        .map(|_| buffer.next_id().unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut all_ids = vec![];
+        for handle in handles {
+            all_ids.extend(handle.join().unwrap());
+        }
+        let count = all_ids.len();
+        all_ids.sort_unstable();
+        all_ids.dedup();
+        assert_eq!(all_ids.len(), count);
+    }
+
+    #[test]
+    fn test_segment_size_adapts_to_rate() {
+        let options = BufferOptions::new()
+            .with_size_range(10, 1000)
+            .with_target_duration(Duration::from_secs(1));
+        let buffer = BufferedIncrement::new(client(), "order-id", options);
+
+        assert_eq!(
+            buffer.inner.adapt_size(100, Duration::from_millis(100)),
+            1000
+        );
+        assert_eq!(buffer.inner.adapt_size(100, Duration::from_secs(2)), 50);
+        assert_eq!(buffer.inner.adapt_size(1, Duration::from_secs(60)), 10);
+    }
+
+    struct RateLimitedHttpClient {
+        requests: AtomicUsize,
+    }
+
+    impl HttpClient for RateLimitedHttpClient {
+        fn get(&self, _url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(Response::new(429, String::new()).with_header("Retry-After", "60"))
+        }
+
+        fn post(&self, _url: &str, _headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_pauses_fetches() {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        let http = RateLimitedHttpClient {
+            requests: AtomicUsize::new(0),
+        };
+        let client = IdBuilderClient::with_http_client(config, http);
+        let buffer = BufferedIncrement::new(client.clone(), "order-id", BufferOptions::new());
+
+        let err = buffer.next_id().unwrap_err();
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(60)));
+
+        let err = buffer.next_id().unwrap_err();
+        assert!(err
+            .retry_after()
+            .is_some_and(|delay| delay <= Duration::from_secs(60)));
+        assert_eq!(client.http_client().requests.load(Ordering::SeqCst), 1);
+    }
+
+    struct EmptyHttpClient {
+        requests: AtomicUsize,
+    }
+
+    impl HttpClient for EmptyHttpClient {
+        fn get(&self, _url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let body = r#"{"code":0,"message":"success","data":{"ids":[]}}"#;
+            Ok(Response::new(200, body.to_string()))
+        }
+
+        fn post(&self, _url: &str, _headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_empty_refill_is_an_error() {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        let http = EmptyHttpClient {
+            requests: AtomicUsize::new(0),
+        };
+        let client = IdBuilderClient::with_http_client(config, http);
+        let buffer = BufferedIncrement::new(client.clone(), "order-id", BufferOptions::new());
+
+        assert!(matches!(
+            buffer.next_id(),
+            Err(crate::Error::SequenceExhausted(key)) if key == "order-id"
+        ));
+        assert_eq!(client.http_client().requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,81 @@
+//! Client-side buffering of server-allocated IDs.
+//!
+//! Buffers fetch IDs from the server in batches and hand them out locally,
+//! refilling in the background before they run dry.
+//!
+//! When the server rate limits a refill, further refills pause for the delay
+//! it asked for, so that a drained buffer does not hammer the server. After
+//! any other failure, background refills wait [`FAILED_REFILL_PAUSE`] before
+//! trying again.
+
+use std::time::{Duration, Instant};
+
+use crate::metrics::{Counter, Histogram, Metrics};
+use crate::{Error, RateLimit, Result};
+
+mod formatted;
+mod increment;
+
+pub use formatted::{FormattedIdPool, PoolOptions};
+pub use increment::{BufferOptions, BufferedIncrement};
+
+/// Pause after a rate limited refill without a `Retry-After` header.
+const DEFAULT_RATE_LIMIT_PAUSE: Duration = Duration::from_secs(1);
+
+/// Pause before the next background refill after one failed.
+const FAILED_REFILL_PAUSE: Duration = Duration::from_secs(1);
+
+/// Get the time until which background refills wait after `result`, if it
+/// failed.
+fn backoff_after<T>(result: &Result<T>) -> Option<Instant> {
+    result.as_ref().err().map(|err| {
+        let backoff = Instant::now() + FAILED_REFILL_PAUSE;
+        pause_after(err).map_or(backoff, |paused_until| paused_until.max(backoff))
+    })
+}
+
+/// Get the time until which refills pause after a failed fetch.
+fn pause_after(err: &Error) -> Option<Instant> {
+    match err {
+        Error::RateLimited(limit) => {
+            Some(Instant::now() + limit.retry_after.unwrap_or(DEFAULT_RATE_LIMIT_PAUSE))
+        }
+        _ => None,
+    }
+}
+
+/// Get the remaining pause, clearing it once it is over.
+fn remaining_pause(paused_until: &mut Option<Instant>) -> Option<Duration> {
+    let remaining = (*paused_until)?.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        *paused_until = None;
+        return None;
+    }
+    Some(remaining)
+}
+
+/// Treat a refill of `key` that returned no IDs as the key being exhausted,
+/// so that an empty answer is not fetched again in a tight loop.
+fn non_empty<T>(key: &str, result: Result<Vec<T>>) -> Result<Vec<T>> {
+    match result {
+        Ok(ids) if ids.is_empty() => Err(Error::SequenceExhausted(key.to_string())),
+        result => result,
+    }
+}
+
+/// Report a successful refill of `key`.
+#[allow(clippy::cast_precision_loss)]
+fn record_refill<T>(metrics: &Metrics, key: &str, result: &Result<Vec<T>>) {
+    if let Ok(ids) = result {
+        metrics.increment(Counter::BufferRefills, key, 1);
+        metrics.record(Histogram::RefillSize, key, ids.len() as f64);
+    }
+}
+
+/// Error returned instead of fetching while refills are paused.
+fn paused_error(remaining: Duration) -> Error {
+    Error::RateLimited(RateLimit {
+        retry_after: Some(remaining),
+        ..RateLimit::default()
+    })
+}
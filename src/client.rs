@@ -1,12 +1,19 @@
 //! Main client implementation.
 
+use std::sync::Arc;
 use std::time::Duration;
 
-use crate::api::{FormattedApi, IncrementApi, SnowflakeApi};
-use crate::config::{ClientConfig, ClientConfigBuilder};
-use crate::http::HttpClient;
-use crate::Result;
+use crate::api::{AdminApi, FormattedApi, IncrementApi, SnowflakeApi};
+use crate::config::ClientConfig;
+#[cfg(feature = "sync")]
+use crate::config::ClientConfigBuilder;
+use crate::endpoint::{EndpointStatus, Endpoints};
+use crate::handle::{FormattedHandle, IncrementHandle, SnowflakeHandle};
+use crate::metrics::Metrics;
+use crate::{Error, Result};
 
+#[cfg(feature = "async")]
+use crate::http::AsyncHttpClient;
 #[cfg(feature = "sync")]
 use crate::http::SyncHttpClient;
 
@@ -40,12 +47,29 @@ use crate::http::SyncHttpClient;
 ///     Ok(())
 /// }
 /// ```
+///
+/// Cloning the client is cheap: clones share the configuration, endpoint
+/// health and HTTP client.
+#[derive(Debug)]
+pub struct IdBuilderClient<C> {
+    inner: Arc<ClientInner<C>>,
+}
+
 #[derive(Debug)]
-pub struct IdBuilderClient<C: HttpClient> {
+struct ClientInner<C> {
     config: ClientConfig,
+    endpoints: Endpoints,
     http_client: C,
 }
 
+impl<C> Clone for IdBuilderClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
 #[cfg(feature = "sync")]
 impl IdBuilderClient<SyncHttpClient> {
     /// Create a new client with the given base URL and key token.
@@ -61,10 +85,7 @@ impl IdBuilderClient<SyncHttpClient> {
     pub fn new(base_url: impl Into<String>, key_token: impl Into<String>) -> Result<Self> {
         let config = ClientConfig::new(base_url).with_key_token(key_token);
         let http_client = SyncHttpClient::new(config.timeout);
-        Ok(Self {
-            config,
-            http_client,
-        })
+        Ok(Self::with_http_client(config, http_client))
     }
 
     /// Create a new client builder.
@@ -80,33 +101,91 @@ impl IdBuilderClient<SyncHttpClient> {
     /// Returns an error if the configuration is invalid.
     pub fn from_config(config: ClientConfig) -> Result<Self> {
         let http_client = SyncHttpClient::new(config.timeout);
-        Ok(Self {
-            config,
-            http_client,
-        })
+        Ok(Self::with_http_client(config, http_client))
     }
 }
 
-impl<C: HttpClient> IdBuilderClient<C> {
+#[cfg(feature = "async")]
+impl IdBuilderClient<AsyncHttpClient> {
+    /// Create a new async client with the given base URL and key token.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Base URL of the `IDBuilder` service
+    /// * `key_token` - Key token for ID generation
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use idbuilder::{IdBuilderClient, Result};
+    ///
+    /// # async fn run() -> Result<()> {
+    /// let client = IdBuilderClient::new_async("http://localhost:8080", "my-key-token")?;
+    /// let ids = client.increment("order-id").generate_async(5).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_async(base_url: impl Into<String>, key_token: impl Into<String>) -> Result<Self> {
+        let config = ClientConfig::new(base_url).with_key_token(key_token);
+        Self::from_config_async(config)
+    }
+
+    /// Create an async client from a configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created.
+    pub fn from_config_async(config: ClientConfig) -> Result<Self> {
+        let http_client = AsyncHttpClient::new(config.timeout)?;
+        Ok(Self::with_http_client(config, http_client))
+    }
+}
+
+impl<C> IdBuilderClient<C> {
     /// Create a new client with a custom HTTP client.
     #[must_use]
-    pub const fn with_http_client(config: ClientConfig, http_client: C) -> Self {
+    pub fn with_http_client(config: ClientConfig, http_client: C) -> Self {
         Self {
-            config,
-            http_client,
+            inner: Arc::new(ClientInner {
+                endpoints: Endpoints::new(&config),
+                config,
+                http_client,
+            }),
         }
     }
 
     /// Get the base URL.
     #[must_use]
     pub fn base_url(&self) -> &str {
-        &self.config.base_url
+        &self.inner.config.base_url
+    }
+
+    /// Get the health of the configured endpoints, in configuration order.
+    #[must_use]
+    pub fn endpoint_status(&self) -> Vec<EndpointStatus> {
+        self.inner.endpoints.status()
+    }
+
+    /// Get the underlying HTTP client.
+    #[must_use]
+    pub fn http_client(&self) -> &C {
+        &self.inner.http_client
     }
 
     /// Get the request timeout.
     #[must_use]
-    pub const fn timeout(&self) -> Duration {
-        self.config.timeout
+    pub fn timeout(&self) -> Duration {
+        self.inner.config.timeout
+    }
+
+    /// Get the metrics the client reports to.
+    #[must_use]
+    pub fn metrics(&self) -> &Metrics {
+        &self.inner.config.metrics
     }
 
     /// Access the auto-increment ID generation API for a specific key.
@@ -117,14 +196,36 @@ impl<C: HttpClient> IdBuilderClient<C> {
     ///
     /// # Panics
     ///
-    /// Panics if no key token is configured.
+    /// Panics if no key token is configured, see
+    /// [`try_increment`](Self::try_increment) for a non-panicking variant.
     pub fn increment(&self, key: impl Into<String>) -> IncrementApi<'_, C> {
-        let key_token = self
-            .config
-            .key_token
-            .as_deref()
-            .expect("Key token is required for ID generation");
-        IncrementApi::new(&self.config.base_url, key_token, &self.http_client, key)
+        self.try_increment(key)
+            .expect("Key token is required for ID generation")
+    }
+
+    /// Access the auto-increment ID generation API for a specific key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if no key token is configured.
+    pub fn try_increment(&self, key: impl Into<String>) -> Result<IncrementApi<'_, C>> {
+        Ok(self.increment_with_token(key, self.key_token()?))
+    }
+
+    /// Access the auto-increment ID generation API for a specific key,
+    /// authorized with the given key token instead of the configured one.
+    pub fn increment_with_token<'a>(
+        &'a self,
+        key: impl Into<String>,
+        key_token: &'a str,
+    ) -> IncrementApi<'a, C> {
+        IncrementApi::new(
+            &self.inner.config,
+            &self.inner.endpoints,
+            key_token,
+            &self.inner.http_client,
+            key,
+        )
     }
 
     /// Access the snowflake ID generation API for a specific key.
@@ -135,14 +236,36 @@ impl<C: HttpClient> IdBuilderClient<C> {
     ///
     /// # Panics
     ///
-    /// Panics if no key token is configured.
+    /// Panics if no key token is configured, see
+    /// [`try_snowflake`](Self::try_snowflake) for a non-panicking variant.
     pub fn snowflake(&self, key: impl Into<String>) -> SnowflakeApi<'_, C> {
-        let key_token = self
-            .config
-            .key_token
-            .as_deref()
-            .expect("Key token is required for ID generation");
-        SnowflakeApi::new(&self.config.base_url, key_token, &self.http_client, key)
+        self.try_snowflake(key)
+            .expect("Key token is required for ID generation")
+    }
+
+    /// Access the snowflake ID generation API for a specific key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if no key token is configured.
+    pub fn try_snowflake(&self, key: impl Into<String>) -> Result<SnowflakeApi<'_, C>> {
+        Ok(self.snowflake_with_token(key, self.key_token()?))
+    }
+
+    /// Access the snowflake ID generation API for a specific key,
+    /// authorized with the given key token instead of the configured one.
+    pub fn snowflake_with_token<'a>(
+        &'a self,
+        key: impl Into<String>,
+        key_token: &'a str,
+    ) -> SnowflakeApi<'a, C> {
+        SnowflakeApi::new(
+            &self.inner.config,
+            &self.inner.endpoints,
+            key_token,
+            &self.inner.http_client,
+            key,
+        )
     }
 
     /// Access the formatted ID generation API for a specific key.
@@ -153,21 +276,110 @@ impl<C: HttpClient> IdBuilderClient<C> {
     ///
     /// # Panics
     ///
-    /// Panics if no key token is configured.
+    /// Panics if no key token is configured, see
+    /// [`try_formatted`](Self::try_formatted) for a non-panicking variant.
     pub fn formatted(&self, key: impl Into<String>) -> FormattedApi<'_, C> {
-        let key_token = self
-            .config
-            .key_token
-            .as_deref()
-            .expect("Key token is required for ID generation");
-        FormattedApi::new(&self.config.base_url, key_token, &self.http_client, key)
+        self.try_formatted(key)
+            .expect("Key token is required for ID generation")
+    }
+
+    /// Access the formatted ID generation API for a specific key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if no key token is configured.
+    pub fn try_formatted(&self, key: impl Into<String>) -> Result<FormattedApi<'_, C>> {
+        Ok(self.formatted_with_token(key, self.key_token()?))
+    }
+
+    /// Access the formatted ID generation API for a specific key,
+    /// authorized with the given key token instead of the configured one.
+    pub fn formatted_with_token<'a>(
+        &'a self,
+        key: impl Into<String>,
+        key_token: &'a str,
+    ) -> FormattedApi<'a, C> {
+        FormattedApi::new(
+            &self.inner.config,
+            &self.inner.endpoints,
+            key_token,
+            &self.inner.http_client,
+            key,
+        )
+    }
+
+    /// Create an owned handle to the auto-increment API of a key.
+    ///
+    /// Unlike [`increment`](Self::increment), the handle does not borrow the
+    /// client and can be stored or moved into another thread or task.
+    #[must_use]
+    pub fn increment_handle(&self, key: impl Into<String>) -> IncrementHandle<C> {
+        IncrementHandle::new(self.clone(), key.into())
+    }
+
+    /// Create an owned handle to the snowflake API of a key.
+    ///
+    /// See [`increment_handle`](Self::increment_handle).
+    #[must_use]
+    pub fn snowflake_handle(&self, key: impl Into<String>) -> SnowflakeHandle<C> {
+        SnowflakeHandle::new(self.clone(), key.into())
+    }
+
+    /// Create an owned handle to the formatted ID API of a key.
+    ///
+    /// See [`increment_handle`](Self::increment_handle).
+    #[must_use]
+    pub fn formatted_handle(&self, key: impl Into<String>) -> FormattedHandle<C> {
+        FormattedHandle::new(self.clone(), key.into())
+    }
+
+    /// Access the admin API for managing key configurations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no admin token is configured, see
+    /// [`try_admin`](Self::try_admin) for a non-panicking variant.
+    #[must_use]
+    pub fn admin(&self) -> AdminApi<'_, C> {
+        self.try_admin()
+            .expect("Admin token is required for key management")
+    }
+
+    /// Access the admin API for managing key configurations.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if no admin token is configured.
+    pub fn try_admin(&self) -> Result<AdminApi<'_, C>> {
+        let admin_token = self.inner.config.admin_token.as_deref().ok_or_else(|| {
+            Error::InvalidConfig("admin_token is required for key management".to_string())
+        })?;
+        Ok(self.admin_with_token(admin_token))
+    }
+
+    /// Access the admin API, authorized with the given admin token instead
+    /// of the configured one.
+    #[must_use]
+    pub fn admin_with_token<'a>(&'a self, admin_token: &'a str) -> AdminApi<'a, C> {
+        AdminApi::new(
+            &self.inner.config,
+            &self.inner.endpoints,
+            admin_token,
+            &self.inner.http_client,
+        )
+    }
+
+    fn key_token(&self) -> Result<&str> {
+        self.inner.config.key_token.as_deref().ok_or_else(|| {
+            Error::InvalidConfig("key_token is required for ID generation".to_string())
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::http::Response;
+    use crate::http::{HttpClient, Response};
 
     struct MockHttpClient;
 
@@ -204,4 +416,198 @@ mod tests {
         let _snowflake_api = client.snowflake("test-key");
         let _formatted_api = client.formatted("test-key");
     }
+
+    #[test]
+    fn test_generate_with_mock() {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        let client = IdBuilderClient::with_http_client(config, MockHttpClient);
+
+        let ids = client.increment("test-key").generate(3).unwrap();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_missing_tokens_are_errors() {
+        let client = IdBuilderClient::with_http_client(
+            ClientConfig::new("http://localhost:8080"),
+            MockHttpClient,
+        );
+
+        assert!(matches!(
+            client.try_increment("test-key"),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(client.try_admin(), Err(Error::InvalidConfig(_))));
+    }
+
+    /// Only accepts the `tenant-b` token.
+    struct TenantHttpClient;
+
+    impl HttpClient for TenantHttpClient {
+        fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+            if headers.contains(&("Authorization", "tenant-b")) {
+                MockHttpClient.get(url, headers)
+            } else {
+                Ok(Response::new(401, String::new()))
+            }
+        }
+
+        fn post(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response> {
+            MockHttpClient.post(url, headers, body)
+        }
+    }
+
+    #[test]
+    fn test_per_call_token_override() {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("tenant-a");
+        let client = IdBuilderClient::with_http_client(config, TenantHttpClient);
+
+        assert!(matches!(
+            client.increment("test-key").generate(3),
+            Err(Error::Unauthorized)
+        ));
+        let ids = client
+            .increment_with_token("test-key", "tenant-b")
+            .generate(3)
+            .unwrap();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    /// Refuses connections to the primary endpoint.
+    struct PrimaryDownHttpClient;
+
+    impl HttpClient for PrimaryDownHttpClient {
+        fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+            if url.starts_with("http://primary") {
+                return Err(crate::error::HttpError::Connection("refused".to_string()).into());
+            }
+            MockHttpClient.get(url, headers)
+        }
+
+        fn post(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response> {
+            MockHttpClient.post(url, headers, body)
+        }
+    }
+
+    #[test]
+    fn test_failover_to_secondary_endpoint() {
+        let config = ClientConfig::new("http://primary:8080")
+            .with_endpoints(["http://secondary:8080"])
+            .with_key_token("test-token");
+        let client = IdBuilderClient::with_http_client(config, PrimaryDownHttpClient);
+
+        let ids = client.increment("test-key").generate(3).unwrap();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        let status = client.endpoint_status();
+        assert!(!status[0].healthy);
+        assert!(status[1].healthy);
+    }
+
+    /// Rate limits every request for an hour.
+    struct RateLimitedHttpClient;
+
+    impl HttpClient for RateLimitedHttpClient {
+        fn get(&self, _url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+            Ok(Response::new(429, String::new()).with_header("Retry-After", "3600"))
+        }
+
+        fn post(&self, url: &str, headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+            self.get(url, headers)
+        }
+    }
+
+    #[test]
+    fn test_all_apis_report_rate_limits() {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        let client = IdBuilderClient::with_http_client(config, RateLimitedHttpClient);
+
+        assert!(matches!(
+            client.increment("test-key").generate(3),
+            Err(Error::RateLimited(_))
+        ));
+        assert!(matches!(
+            client.formatted("test-key").generate(3),
+            Err(Error::RateLimited(_))
+        ));
+        assert!(matches!(
+            client.snowflake("test-key").get_config(),
+            Err(Error::RateLimited(_))
+        ));
+        assert!(matches!(
+            client.snowflake("test-key").heartbeat(1),
+            Err(Error::RateLimited(_))
+        ));
+    }
+
+    #[test]
+    fn test_calls_are_reported_to_metrics() {
+        use crate::{Counter, ErrorKind, MetricsRecorder};
+
+        let recorder = MetricsRecorder::new();
+        let config = ClientConfig::new("http://localhost:8080")
+            .with_key_token("tenant-a")
+            .with_metrics(recorder.clone());
+        let client = IdBuilderClient::with_http_client(config, TenantHttpClient);
+
+        client
+            .increment_with_token("order-id", "tenant-b")
+            .generate(3)
+            .unwrap();
+        client.increment("order-id").generate(3).unwrap_err();
+
+        let snapshot = recorder.snapshot();
+        assert_eq!(snapshot.counter(Counter::Calls, "order-id"), 2);
+        assert_eq!(snapshot.counter(Counter::IdsIssued, "order-id"), 3);
+        assert_eq!(
+            snapshot.counter(Counter::Errors(ErrorKind::Auth), "order-id"),
+            1
+        );
+    }
+
+    #[cfg(feature = "async")]
+    struct MockAsyncHttpClient;
+
+    #[cfg(feature = "async")]
+    impl crate::http::AsyncHttpTransport for MockAsyncHttpClient {
+        async fn get(&self, _url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+            Ok(Response::new(
+                200,
+                r#"{"code":0,"message":"success","data":{"ids":[1,2,3]}}"#.to_string(),
+            ))
+        }
+
+        async fn post(
+            &self,
+            _url: &str,
+            _headers: &[(&str, &str)],
+            _body: &str,
+        ) -> Result<Response> {
+            Ok(Response::new(
+                200,
+                r#"{"code":0,"message":"success","data":null}"#.to_string(),
+            ))
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_generate_async_with_mock() {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        let client = IdBuilderClient::with_http_client(config, MockAsyncHttpClient);
+
+        let ids = client
+            .increment("test-key")
+            .generate_async(3)
+            .await
+            .unwrap();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        let id = client
+            .increment("test-key")
+            .generate_one_async()
+            .await
+            .unwrap();
+        assert_eq!(id, 1);
+    }
 }
@@ -0,0 +1,196 @@
+//! Time sources for local snowflake generation.
+//!
+//! A [`SnowflakeGenerator`](crate::SnowflakeGenerator) reads time through the
+//! [`Clock`] trait, so the time source can be chosen per generator:
+//!
+//! - [`SystemClock`] reads the wall clock (default)
+//! - [`MonotonicClock`] anchors the wall clock once and advances it with a
+//!   monotonic timer, so it never moves backwards
+//! - [`MockClock`] only moves when told to, for deterministic tests
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{Error, Result};
+
+/// Source of the current time in milliseconds since the Unix epoch.
+pub trait Clock {
+    /// Get the current time in milliseconds since the Unix epoch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the time cannot be determined.
+    fn now_millis(&self) -> Result<i64>;
+
+    /// Block until the clock reaches `millis`, returning the time reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the time cannot be determined.
+    fn wait_until(&self, millis: i64) -> Result<i64> {
+        loop {
+            let now = self.now_millis()?;
+            if now >= millis {
+                return Ok(now);
+            }
+            if millis - now > 1 {
+                std::thread::sleep(Duration::from_millis(1));
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
+
+/// Wall clock based on [`SystemTime`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> Result<i64> {
+        system_millis()
+    }
+}
+
+/// Clock anchored to the wall clock once and advanced by a monotonic timer.
+///
+/// It never observes the system clock moving backwards, at the cost of
+/// drifting from the wall clock when it is adjusted after creation.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    anchor_millis: i64,
+    anchor: Instant,
+}
+
+impl MonotonicClock {
+    /// Create a clock anchored to the current wall clock time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the system time is before the Unix epoch.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            anchor_millis: system_millis()?,
+            anchor: Instant::now(),
+        })
+    }
+}
+
+impl Clock for MonotonicClock {
+    #[allow(clippy::cast_possible_truncation)]
+    fn now_millis(&self) -> Result<i64> {
+        Ok(self.anchor_millis + self.anchor.elapsed().as_millis() as i64)
+    }
+}
+
+/// Manually controlled clock for tests.
+///
+/// Clones share the same time, so a test can keep a handle and move the
+/// clock of a generator it has handed the clock to. Waiting on a mock clock
+/// advances it to the awaited time instead of blocking.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use idbuilder::{MockClock, SnowflakeGenerator};
+///
+/// let clock = MockClock::new(1_704_067_200_000);
+/// let generator = SnowflakeGenerator::new(1, 1_704_067_200_000, 10, 12)
+///     .with_clock(clock.clone());
+///
+/// let first = generator.next_id().unwrap();
+/// clock.advance(Duration::from_millis(1));
+/// let second = generator.next_id().unwrap();
+/// assert!(second > first);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    millis: Arc<AtomicI64>,
+}
+
+impl MockClock {
+    /// Create a clock set to the given time in milliseconds since the Unix epoch.
+    #[must_use]
+    pub fn new(millis: i64) -> Self {
+        Self {
+            millis: Arc::new(AtomicI64::new(millis)),
+        }
+    }
+
+    /// Set the current time, possibly moving it backwards.
+    pub fn set(&self, millis: i64) {
+        self.millis.store(millis, Ordering::SeqCst);
+    }
+
+    /// Move the clock forward.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn advance(&self, duration: Duration) {
+        self.millis
+            .fetch_add(duration.as_millis() as i64, Ordering::SeqCst);
+    }
+
+    /// Move the clock backward.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn rewind(&self, duration: Duration) {
+        self.millis
+            .fetch_sub(duration.as_millis() as i64, Ordering::SeqCst);
+    }
+
+    /// Get the current time.
+    #[must_use]
+    pub fn get(&self) -> i64 {
+        self.millis.load(Ordering::SeqCst)
+    }
+}
+
+impl Clock for MockClock {
+    fn now_millis(&self) -> Result<i64> {
+        Ok(self.get())
+    }
+
+    fn wait_until(&self, millis: i64) -> Result<i64> {
+        Ok(self.millis.fetch_max(millis, Ordering::SeqCst).max(millis))
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn system_millis() -> Result<i64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .map_err(|_| Error::ClockMovedBackwards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monotonic_clock_tracks_system_clock() {
+        let clock = MonotonicClock::new().unwrap();
+        let system = SystemClock.now_millis().unwrap();
+        assert!((clock.now_millis().unwrap() - system).abs() < 1000);
+    }
+
+    #[test]
+    fn test_mock_clock_is_shared() {
+        let clock = MockClock::new(1000);
+        let handle = clock.clone();
+
+        handle.advance(Duration::from_millis(5));
+        assert_eq!(clock.now_millis().unwrap(), 1005);
+
+        handle.rewind(Duration::from_millis(10));
+        assert_eq!(clock.now_millis().unwrap(), 995);
+    }
+
+    #[test]
+    fn test_mock_clock_wait_advances() {
+        let clock = MockClock::new(1000);
+        assert_eq!(clock.wait_until(1010).unwrap(), 1010);
+        assert_eq!(clock.wait_until(1005).unwrap(), 1010);
+        assert_eq!(clock.get(), 1010);
+    }
+}
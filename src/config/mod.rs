@@ -0,0 +1,283 @@
+//! Client configuration.
+
+use std::time::Duration;
+
+use crate::endpoint::EndpointStrategy;
+use crate::metrics::Metrics;
+use crate::retry::RetryPolicy;
+
+mod settings;
+
+pub use settings::{ClientProfiles, ClientSettings};
+
+/// Configuration for the `IDBuilder` client.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Base URL of the `IDBuilder` service.
+    pub base_url: String,
+
+    /// Additional endpoints of the service, used alongside `base_url`
+    /// according to `endpoint_strategy`.
+    pub endpoints: Vec<String>,
+
+    /// How requests are spread across `base_url` and `endpoints`.
+    pub endpoint_strategy: EndpointStrategy,
+
+    /// How long an unreachable endpoint is skipped before it is probed again.
+    pub recovery_interval: Duration,
+
+    /// Key token for ID generation.
+    pub key_token: Option<String>,
+
+    /// Admin token for managing key configurations.
+    pub admin_token: Option<String>,
+
+    /// Request timeout.
+    pub timeout: Duration,
+
+    /// Number of retries for failed requests.
+    pub retries: u32,
+
+    /// Backoff and retryability rules applied when `retries` is non-zero.
+    pub retry_policy: RetryPolicy,
+
+    /// Where API calls and buffer refills are reported; disabled by default.
+    pub metrics: Metrics,
+}
+
+impl ClientConfig {
+    /// Default request timeout (30 seconds).
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Default number of retries.
+    pub const DEFAULT_RETRIES: u32 = 0;
+
+    /// Default interval before an unreachable endpoint is probed (30 seconds).
+    pub const DEFAULT_RECOVERY_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Create a new configuration with the given base URL.
+    #[must_use]
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            endpoints: Vec::new(),
+            endpoint_strategy: EndpointStrategy::default(),
+            recovery_interval: Self::DEFAULT_RECOVERY_INTERVAL,
+            key_token: None,
+            admin_token: None,
+            timeout: Self::DEFAULT_TIMEOUT,
+            retries: Self::DEFAULT_RETRIES,
+            retry_policy: RetryPolicy::new(),
+            metrics: Metrics::disabled(),
+        }
+    }
+
+    /// Create a new configuration builder.
+    #[must_use]
+    pub fn builder() -> ClientConfigBuilder {
+        ClientConfigBuilder::new()
+    }
+
+    /// Add fallback endpoints of the service.
+    #[must_use]
+    pub fn with_endpoints<I, S>(mut self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.endpoints.extend(urls.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set the endpoint selection strategy.
+    #[must_use]
+    pub const fn with_endpoint_strategy(mut self, strategy: EndpointStrategy) -> Self {
+        self.endpoint_strategy = strategy;
+        self
+    }
+
+    /// Set how long an unreachable endpoint is skipped before it is probed again.
+    #[must_use]
+    pub const fn with_recovery_interval(mut self, interval: Duration) -> Self {
+        self.recovery_interval = interval;
+        self
+    }
+
+    /// Set the key token.
+    #[must_use]
+    pub fn with_key_token(mut self, token: impl Into<String>) -> Self {
+        self.key_token = Some(token.into());
+        self
+    }
+
+    /// Set the admin token.
+    #[must_use]
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    /// Set the request timeout.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the number of retries.
+    #[must_use]
+    pub const fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set the retry policy.
+    #[must_use]
+    pub const fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Report metrics to the given sink.
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: impl Into<Metrics>) -> Self {
+        self.metrics = metrics.into();
+        self
+    }
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: String::new(),
+            endpoints: Vec::new(),
+            endpoint_strategy: EndpointStrategy::default(),
+            recovery_interval: Self::DEFAULT_RECOVERY_INTERVAL,
+            key_token: None,
+            admin_token: None,
+            timeout: Self::DEFAULT_TIMEOUT,
+            retries: Self::DEFAULT_RETRIES,
+            retry_policy: RetryPolicy::new(),
+            metrics: Metrics::disabled(),
+        }
+    }
+}
+
+/// Builder for [`ClientConfig`].
+#[derive(Debug, Default)]
+pub struct ClientConfigBuilder {
+    base_url: Option<String>,
+    endpoints: Vec<String>,
+    endpoint_strategy: Option<EndpointStrategy>,
+    recovery_interval: Option<Duration>,
+    key_token: Option<String>,
+    admin_token: Option<String>,
+    timeout: Option<Duration>,
+    retries: Option<u32>,
+    retry_policy: Option<RetryPolicy>,
+    metrics: Metrics,
+}
+
+impl ClientConfigBuilder {
+    /// Create a new builder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the base URL.
+    #[must_use]
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Add a fallback endpoint.
+    #[must_use]
+    pub fn endpoint(mut self, url: impl Into<String>) -> Self {
+        self.endpoints.push(url.into());
+        self
+    }
+
+    /// Set the endpoint selection strategy.
+    #[must_use]
+    pub const fn endpoint_strategy(mut self, strategy: EndpointStrategy) -> Self {
+        self.endpoint_strategy = Some(strategy);
+        self
+    }
+
+    /// Set how long an unreachable endpoint is skipped before it is probed again.
+    #[must_use]
+    pub const fn recovery_interval(mut self, interval: Duration) -> Self {
+        self.recovery_interval = Some(interval);
+        self
+    }
+
+    /// Set the key token.
+    #[must_use]
+    pub fn key_token(mut self, token: impl Into<String>) -> Self {
+        self.key_token = Some(token.into());
+        self
+    }
+
+    /// Set the admin token.
+    #[must_use]
+    pub fn admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    /// Set the request timeout.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the number of retries.
+    #[must_use]
+    pub const fn retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// Set the retry policy.
+    #[must_use]
+    pub const fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Report metrics to the given sink.
+    #[must_use]
+    pub fn metrics(mut self, metrics: impl Into<Metrics>) -> Self {
+        self.metrics = metrics.into();
+        self
+    }
+
+    /// Build the configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the base URL is not set.
+    pub fn build(self) -> crate::Result<ClientConfig> {
+        let base_url = self
+            .base_url
+            .ok_or_else(|| crate::Error::InvalidConfig("base_url is required".to_string()))?;
+
+        Ok(ClientConfig {
+            base_url,
+            endpoints: self.endpoints,
+            endpoint_strategy: self.endpoint_strategy.unwrap_or_default(),
+            recovery_interval: self
+                .recovery_interval
+                .unwrap_or(ClientConfig::DEFAULT_RECOVERY_INTERVAL),
+            key_token: self.key_token,
+            admin_token: self.admin_token,
+            timeout: self.timeout.unwrap_or(ClientConfig::DEFAULT_TIMEOUT),
+            retries: self.retries.unwrap_or(ClientConfig::DEFAULT_RETRIES),
+            retry_policy: self.retry_policy.unwrap_or_default(),
+            metrics: self.metrics,
+        })
+    }
+}
@@ -0,0 +1,433 @@
+//! Client configuration from environment variables and configuration files.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::ClientConfig;
+use crate::endpoint::EndpointStrategy;
+use crate::{Error, Result};
+
+const ENV_URL: &str = "IDBUILDER_URL";
+const ENV_KEY_TOKEN: &str = "IDBUILDER_KEY_TOKEN";
+const ENV_ADMIN_TOKEN: &str = "IDBUILDER_ADMIN_TOKEN";
+const ENV_TIMEOUT: &str = "IDBUILDER_TIMEOUT";
+const ENV_RETRIES: &str = "IDBUILDER_RETRIES";
+
+/// Client settings as written in a configuration file.
+///
+/// Every field is optional, so settings can be layered with
+/// [`or`](Self::or) before they are validated into a [`ClientConfig`].
+/// Durations are written with a unit, such as `500ms`, `30s`, `5m` or `1h`,
+/// or as a number of seconds.
+///
+/// ```
+/// use idbuilder::ClientSettings;
+///
+/// let settings: ClientSettings = serde_json::from_str(
+///     r#"{"url": "http://localhost:8080", "key_token": "my-key-token", "timeout": "5s"}"#,
+/// )?;
+/// let config = settings.into_config()?;
+/// assert_eq!(config.timeout, std::time::Duration::from_secs(5));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientSettings {
+    /// Base URL of the service.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// Additional endpoints of the service.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<String>,
+
+    /// How requests are spread across the endpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint_strategy: Option<EndpointStrategy>,
+
+    /// How long an unreachable endpoint is skipped, e.g. `30s`.
+    #[serde(
+        deserialize_with = "duration_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_interval: Option<String>,
+
+    /// Key token for ID generation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_token: Option<String>,
+
+    /// Admin token for managing key configurations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
+
+    /// Request timeout, e.g. `30s`.
+    #[serde(
+        deserialize_with = "duration_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timeout: Option<String>,
+
+    /// Number of retries for failed requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+}
+
+impl ClientSettings {
+    /// Fill the settings not given here from `fallback`.
+    #[must_use]
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            url: self.url.or(fallback.url),
+            endpoints: if self.endpoints.is_empty() {
+                fallback.endpoints
+            } else {
+                self.endpoints
+            },
+            endpoint_strategy: self.endpoint_strategy.or(fallback.endpoint_strategy),
+            recovery_interval: self.recovery_interval.or(fallback.recovery_interval),
+            key_token: self.key_token.or(fallback.key_token),
+            admin_token: self.admin_token.or(fallback.admin_token),
+            timeout: self.timeout.or(fallback.timeout),
+            retries: self.retries.or(fallback.retries),
+        }
+    }
+
+    /// Validate the settings into a client configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] naming the first invalid field, e.g.
+    /// `timeout: invalid duration "soon"`.
+    pub fn into_config(self) -> Result<ClientConfig> {
+        self.validate(&str::to_string)
+    }
+
+    /// Validate the settings, naming fields in errors with `field`.
+    fn validate(self, field: &dyn Fn(&str) -> String) -> Result<ClientConfig> {
+        let invalid = |name: &str, problem: String| {
+            Error::InvalidConfig(format!("{}: {problem}", field(name)))
+        };
+
+        let url = self
+            .url
+            .ok_or_else(|| invalid("url", "is required".to_string()))?;
+        check_url(&url).map_err(|problem| invalid("url", problem))?;
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            check_url(endpoint)
+                .map_err(|problem| invalid(&format!("endpoints[{index}]"), problem))?;
+        }
+        for (name, token) in [
+            ("key_token", &self.key_token),
+            ("admin_token", &self.admin_token),
+        ] {
+            if token.as_ref().is_some_and(|token| token.trim().is_empty()) {
+                return Err(invalid(name, "must not be empty".to_string()));
+            }
+        }
+        let timeout = self
+            .timeout
+            .map(|text| parse_duration(&text).map_err(|problem| invalid("timeout", problem)))
+            .transpose()?;
+        let recovery_interval = self
+            .recovery_interval
+            .map(|text| {
+                parse_duration(&text).map_err(|problem| invalid("recovery_interval", problem))
+            })
+            .transpose()?;
+
+        let mut config = ClientConfig::new(url)
+            .with_endpoints(self.endpoints)
+            .with_retries(self.retries.unwrap_or(ClientConfig::DEFAULT_RETRIES));
+        config.key_token = self.key_token;
+        config.admin_token = self.admin_token;
+        if let Some(strategy) = self.endpoint_strategy {
+            config = config.with_endpoint_strategy(strategy);
+        }
+        if let Some(timeout) = timeout {
+            config = config.with_timeout(timeout);
+        }
+        if let Some(interval) = recovery_interval {
+            config = config.with_recovery_interval(interval);
+        }
+        Ok(config)
+    }
+}
+
+/// Named client settings, e.g. one profile per environment.
+///
+/// ```toml
+/// [profiles.default]
+/// url = "http://localhost:8080"
+/// key_token = "dev-key-token"
+///
+/// [profiles.production]
+/// url = "https://idbuilder-1.example.com"
+/// endpoints = ["https://idbuilder-2.example.com"]
+/// endpoint_strategy = "round-robin"
+/// key_token = "prod-key-token"
+/// timeout = "5s"
+/// retries = 3
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientProfiles {
+    /// Settings by profile name.
+    pub profiles: BTreeMap<String, ClientSettings>,
+}
+
+impl ClientProfiles {
+    /// Conventional name of the profile used when none is chosen.
+    pub const DEFAULT_PROFILE: &'static str = "default";
+
+    /// Validate the profile `name` into a client configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if the profile does not exist, or
+    /// naming the first invalid field, e.g. `profiles.production.timeout`.
+    pub fn config(&self, name: &str) -> Result<ClientConfig> {
+        let settings = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| Error::InvalidConfig(format!("profiles.{name}: not defined")))?;
+        settings
+            .clone()
+            .validate(&|field| format!("profiles.{name}.{field}"))
+    }
+}
+
+impl ClientConfig {
+    /// Create a configuration from environment variables.
+    ///
+    /// | Variable | Setting |
+    /// |----------|---------|
+    /// | `IDBUILDER_URL` | Base URL (required) |
+    /// | `IDBUILDER_KEY_TOKEN` | Key token |
+    /// | `IDBUILDER_ADMIN_TOKEN` | Admin token |
+    /// | `IDBUILDER_TIMEOUT` | Request timeout, e.g. `30s`, `500ms` or `30` seconds |
+    /// | `IDBUILDER_RETRIES` | Number of retries |
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] naming the variable that is missing or
+    /// invalid.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Create a configuration from the variables returned by `var`.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let retries = var(ENV_RETRIES)
+            .map(|text| {
+                text.trim().parse().map_err(|_| {
+                    Error::InvalidConfig(format!("{ENV_RETRIES}: invalid number {text:?}"))
+                })
+            })
+            .transpose()?;
+        let settings = ClientSettings {
+            url: var(ENV_URL),
+            key_token: var(ENV_KEY_TOKEN),
+            admin_token: var(ENV_ADMIN_TOKEN),
+            timeout: var(ENV_TIMEOUT),
+            retries,
+            ..ClientSettings::default()
+        };
+        settings.validate(&|field| {
+            match field {
+                "url" => ENV_URL,
+                "key_token" => ENV_KEY_TOKEN,
+                "admin_token" => ENV_ADMIN_TOKEN,
+                "timeout" => ENV_TIMEOUT,
+                _ => field,
+            }
+            .to_string()
+        })
+    }
+}
+
+fn check_url(url: &str) -> std::result::Result<(), String> {
+    let host = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"))
+        .ok_or_else(|| format!("{url:?} is not an http or https URL"))?;
+    if host.is_empty() || host.starts_with('/') {
+        return Err(format!("{url:?} has no host"));
+    }
+    Ok(())
+}
+
+/// Parse a duration such as `500ms`, `30s`, `5m`, `1h` or `30` (seconds).
+fn parse_duration(text: &str) -> std::result::Result<Duration, String> {
+    let invalid = || format!("invalid duration {text:?}");
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (amount, unit) = text.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let duration = match unit.trim() {
+        "ms" => Duration::from_millis(amount),
+        "" | "s" => Duration::from_secs(amount),
+        "m" => Duration::from_secs(amount.saturating_mul(60)),
+        "h" => Duration::from_secs(amount.saturating_mul(3600)),
+        _ => return Err(invalid()),
+    };
+    if duration.is_zero() {
+        return Err("must be greater than zero".to_string());
+    }
+    Ok(duration)
+}
+
+/// Deserialize a duration written as text or as a number of seconds.
+fn duration_text<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Text {
+        Secs(u64),
+        Text(String),
+    }
+
+    Ok(
+        Option::<Text>::deserialize(deserializer)?.map(|text| match text {
+            Text::Secs(secs) => secs.to_string(),
+            Text::Text(text) => text,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn invalid_field(result: Result<ClientConfig>) -> String {
+        match result {
+            Err(Error::InvalidConfig(message)) => message,
+            other => panic!("expected an invalid configuration, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let vars = HashMap::from([
+            (ENV_URL, "http://localhost:8080"),
+            (ENV_KEY_TOKEN, "my-key-token"),
+            (ENV_TIMEOUT, "1500ms"),
+            (ENV_RETRIES, "3"),
+        ]);
+        let config =
+            ClientConfig::from_vars(|name| vars.get(name).map(ToString::to_string)).unwrap();
+
+        assert_eq!(config.base_url, "http://localhost:8080");
+        assert_eq!(config.key_token.as_deref(), Some("my-key-token"));
+        assert_eq!(config.admin_token, None);
+        assert_eq!(config.timeout, Duration::from_millis(1500));
+        assert_eq!(config.retries, 3);
+    }
+
+    #[test]
+    fn test_from_vars_names_the_variable() {
+        let from = |vars: &[(&str, &str)]| {
+            let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+            invalid_field(ClientConfig::from_vars(|name| {
+                vars.get(name).map(ToString::to_string)
+            }))
+        };
+
+        assert_eq!(from(&[]), "IDBUILDER_URL: is required");
+        assert_eq!(
+            from(&[(ENV_URL, "http://a"), (ENV_TIMEOUT, "soon")]),
+            "IDBUILDER_TIMEOUT: invalid duration \"soon\""
+        );
+        assert_eq!(
+            from(&[(ENV_URL, "http://a"), (ENV_RETRIES, "-1")]),
+            "IDBUILDER_RETRIES: invalid number \"-1\""
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        let profiles: ClientProfiles = serde_json::from_str(
+            r#"{"profiles": {
+                "default": {"url": "http://localhost:8080", "timeout": 5},
+                "production": {
+                    "url": "https://idbuilder-1.example.com",
+                    "endpoints": ["https://idbuilder-2.example.com"],
+                    "endpoint_strategy": "round-robin",
+                    "recovery_interval": "1m"
+                },
+                "broken": {"url": "http://a", "endpoints": ["idbuilder-2:8080"]}
+            }}"#,
+        )
+        .unwrap();
+
+        let config = profiles.config(ClientProfiles::DEFAULT_PROFILE).unwrap();
+        assert_eq!(config.timeout, Duration::from_secs(5));
+
+        let config = profiles.config("production").unwrap();
+        assert_eq!(config.endpoints, ["https://idbuilder-2.example.com"]);
+        assert_eq!(config.endpoint_strategy, EndpointStrategy::RoundRobin);
+        assert_eq!(config.recovery_interval, Duration::from_secs(60));
+
+        assert_eq!(
+            invalid_field(profiles.config("broken")),
+            "profiles.broken.endpoints[0]: \"idbuilder-2:8080\" is not an http or https URL"
+        );
+        assert_eq!(
+            invalid_field(profiles.config("staging")),
+            "profiles.staging: not defined"
+        );
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        let settings = ClientSettings {
+            url: Some("http://localhost:8080".to_string()),
+            ..ClientSettings::default()
+        };
+        let with = |change: fn(&mut ClientSettings)| {
+            let mut settings = settings.clone();
+            change(&mut settings);
+            invalid_field(settings.into_config())
+        };
+
+        assert_eq!(
+            with(|s| s.url = Some("https://".to_string())),
+            "url: \"https://\" has no host"
+        );
+        assert_eq!(
+            with(|s| s.key_token = Some(" ".to_string())),
+            "key_token: must not be empty"
+        );
+        assert_eq!(
+            with(|s| s.timeout = Some("0s".to_string())),
+            "timeout: must be greater than zero"
+        );
+        assert_eq!(
+            with(|s| s.recovery_interval = Some("5 days".to_string())),
+            "recovery_interval: invalid duration \"5 days\""
+        );
+    }
+
+    #[test]
+    fn test_or_layers_settings() {
+        let flags = ClientSettings {
+            key_token: Some("flag-token".to_string()),
+            ..ClientSettings::default()
+        };
+        let profile = ClientSettings {
+            url: Some("http://localhost:8080".to_string()),
+            key_token: Some("profile-token".to_string()),
+            ..ClientSettings::default()
+        };
+
+        let config = flags.or(profile).into_config().unwrap();
+        assert_eq!(config.base_url, "http://localhost:8080");
+        assert_eq!(config.key_token.as_deref(), Some("flag-token"));
+    }
+}
@@ -0,0 +1,407 @@
+//! Endpoint selection and failover across several service nodes.
+//!
+//! A client can be configured with more than one `IDBuilder` endpoint. Each
+//! request picks an endpoint according to the [`EndpointStrategy`] and falls
+//! over to the next one when the connection cannot be established.
+//!
+//! Health is tracked passively: an endpoint that fails with
+//! [`HttpError::Connection`] is marked unhealthy and skipped. Once
+//! [`ClientConfig::recovery_interval`] has passed, a single request is sent to
+//! it as a recovery probe; if the probe connects the endpoint is healthy
+//! again, otherwise it sits out another interval.
+//!
+//! Only [`HttpError::Connection`] triggers failover: the transports report
+//! it only when the connection could not be established, so the server is
+//! known not to have seen the request. Timeouts, connections that broke after
+//! the request was sent ([`HttpError::Interrupted`]) and error responses are
+//! returned as-is and left to the [`RetryPolicy`](crate::RetryPolicy), since
+//! sending them to another node could allocate IDs twice.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ClientConfig;
+use crate::error::HttpError;
+use crate::http::Response;
+use crate::{Error, Result};
+
+/// Strategy for choosing between several endpoints.
+///
+/// In configuration files the strategies are written `failover`,
+/// `round-robin` and `least-latency`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EndpointStrategy {
+    /// Always use the first healthy endpoint in configuration order, so the
+    /// remaining endpoints act as secondaries.
+    #[default]
+    Failover,
+
+    /// Spread requests across the healthy endpoints in turn.
+    RoundRobin,
+
+    /// Prefer the healthy endpoint with the lowest observed latency.
+    LeastLatency,
+}
+
+/// Health snapshot of a single endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    /// Base URL of the endpoint.
+    pub url: String,
+
+    /// Whether the endpoint is currently considered healthy.
+    pub healthy: bool,
+
+    /// Smoothed request latency, if any request has completed.
+    pub latency: Option<Duration>,
+}
+
+/// Runtime state of the configured endpoints.
+#[derive(Debug)]
+pub struct Endpoints {
+    entries: Vec<Endpoint>,
+    strategy: EndpointStrategy,
+    recovery_interval: Duration,
+    cursor: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    /// `None` while healthy, otherwise the earliest time of the next probe.
+    next_probe: Mutex<Option<Instant>>,
+    /// Exponentially weighted latency in microseconds, 0 if unknown.
+    latency_micros: AtomicU64,
+}
+
+impl Endpoints {
+    pub fn new(config: &ClientConfig) -> Self {
+        let entries = std::iter::once(&config.base_url)
+            .chain(&config.endpoints)
+            .map(|url| Endpoint {
+                url: url.clone(),
+                next_probe: Mutex::new(None),
+                latency_micros: AtomicU64::new(0),
+            })
+            .collect();
+
+        Self {
+            entries,
+            strategy: config.endpoint_strategy,
+            recovery_interval: config.recovery_interval,
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    /// Health snapshot of all endpoints, in configuration order.
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        self.entries
+            .iter()
+            .map(|endpoint| {
+                let micros = endpoint.latency_micros.load(Ordering::Relaxed);
+                EndpointStatus {
+                    url: endpoint.url.clone(),
+                    healthy: endpoint.next_probe().is_none(),
+                    latency: (micros > 0).then(|| Duration::from_micros(micros)),
+                }
+            })
+            .collect()
+    }
+
+    /// Send a request, failing over to the next endpoint on connection errors.
+    ///
+    /// `send` receives the base URL of the endpoint to use.
+    pub fn send<F>(&self, mut send: F) -> Result<Response>
+    where
+        F: FnMut(&str) -> Result<Response>,
+    {
+        let mut outcome = Err(no_endpoints());
+        for index in self.order() {
+            let started = Instant::now();
+            outcome = send(&self.entries[index].url);
+            if !self.observe(index, &outcome, started) {
+                break;
+            }
+        }
+        outcome
+    }
+
+    /// Async variant of [`send`](Self::send).
+    #[cfg(feature = "async")]
+    pub async fn send_async<F, Fut>(&self, mut send: F) -> Result<Response>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: std::future::Future<Output = Result<Response>>,
+    {
+        let mut outcome = Err(no_endpoints());
+        for index in self.order() {
+            let started = Instant::now();
+            outcome = send(self.entries[index].url.clone()).await;
+            if !self.observe(index, &outcome, started) {
+                break;
+            }
+        }
+        outcome
+    }
+
+    /// Order in which endpoints are tried for the next request.
+    ///
+    /// Endpoints due for a recovery probe come first, then the healthy ones
+    /// in strategy order. Endpoints still sitting out are tried last, so a
+    /// request is never refused without contacting any endpoint.
+    fn order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let mut probes = Vec::new();
+        let mut healthy = Vec::new();
+        let mut unhealthy = Vec::new();
+
+        for (index, endpoint) in self.entries.iter().enumerate() {
+            match endpoint.claim(now, self.recovery_interval) {
+                Claim::Healthy => healthy.push(index),
+                Claim::Probe => probes.push(index),
+                Claim::SittingOut => unhealthy.push(index),
+            }
+        }
+
+        match self.strategy {
+            EndpointStrategy::Failover => {}
+            EndpointStrategy::RoundRobin => {
+                if !healthy.is_empty() {
+                    let start = self.cursor.fetch_add(1, Ordering::Relaxed) % healthy.len();
+                    healthy.rotate_left(start);
+                }
+            }
+            EndpointStrategy::LeastLatency => {
+                // Unmeasured endpoints sort first so they get measured.
+                healthy.sort_by_key(|&index| {
+                    self.entries[index].latency_micros.load(Ordering::Relaxed)
+                });
+            }
+        }
+
+        probes.extend(healthy);
+        probes.extend(unhealthy);
+        probes
+    }
+
+    /// Record the outcome of a request, returning whether to fail over.
+    ///
+    /// Only a failure to connect fails over, see the module documentation.
+    fn observe(&self, index: usize, outcome: &Result<Response>, started: Instant) -> bool {
+        let endpoint = &self.entries[index];
+        match outcome {
+            Err(Error::Http(HttpError::Connection(_))) => {
+                let mut next_probe = endpoint
+                    .next_probe
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                if next_probe.is_none() {
+                    *next_probe = Some(Instant::now() + self.recovery_interval);
+                }
+                true
+            }
+            Ok(_) => {
+                *endpoint
+                    .next_probe
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = None;
+                endpoint.record_latency(started.elapsed());
+                false
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// How an endpoint takes part in the next request.
+enum Claim {
+    Healthy,
+    Probe,
+    SittingOut,
+}
+
+impl Endpoint {
+    /// Check the endpoint's health, claiming a recovery probe if one is due.
+    fn claim(&self, now: Instant, recovery_interval: Duration) -> Claim {
+        let mut next_probe = self
+            .next_probe
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match *next_probe {
+            None => Claim::Healthy,
+            Some(at) if at <= now => {
+                // Push the probe time out so concurrent requests keep skipping it.
+                *next_probe = Some(now + recovery_interval);
+                Claim::Probe
+            }
+            Some(_) => Claim::SittingOut,
+        }
+    }
+
+    fn next_probe(&self) -> Option<Instant> {
+        *self
+            .next_probe
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn record_latency(&self, latency: Duration) {
+        let sample = (latency.as_micros() as u64).max(1);
+        // Weight new samples by 1/4; a lost race only drops one sample.
+        let previous = self.latency_micros.load(Ordering::Relaxed);
+        let smoothed = if previous == 0 {
+            sample
+        } else {
+            (previous * 3 + sample) / 4
+        };
+        self.latency_micros
+            .store(smoothed.max(1), Ordering::Relaxed);
+    }
+}
+
+fn no_endpoints() -> Error {
+    Error::InvalidConfig("no endpoints configured".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(strategy: EndpointStrategy) -> Endpoints {
+        let config = ClientConfig::new("http://a")
+            .with_endpoints(["http://b", "http://c"])
+            .with_endpoint_strategy(strategy)
+            .with_recovery_interval(Duration::from_secs(60));
+        Endpoints::new(&config)
+    }
+
+    fn refuse() -> Result<Response> {
+        Err(HttpError::Connection("refused".to_string()).into())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn ok() -> Result<Response> {
+        Ok(Response::new(200, String::new()))
+    }
+
+    #[test]
+    fn test_failover_uses_primary_then_secondary() {
+        let endpoints = endpoints(EndpointStrategy::Failover);
+
+        let mut seen = Vec::new();
+        endpoints
+            .send(|base| {
+                seen.push(base.to_string());
+                if base == "http://a" {
+                    refuse()
+                } else {
+                    ok()
+                }
+            })
+            .unwrap();
+        assert_eq!(seen, ["http://a", "http://b"]);
+
+        // The primary is now unhealthy and skipped until the next probe.
+        let mut seen = Vec::new();
+        endpoints
+            .send(|base| {
+                seen.push(base.to_string());
+                ok()
+            })
+            .unwrap();
+        assert_eq!(seen, ["http://b"]);
+
+        let status = endpoints.status();
+        assert!(!status[0].healthy);
+        assert!(status[1].healthy);
+        assert!(status[1].latency.is_some());
+    }
+
+    #[test]
+    fn test_all_endpoints_down_returns_last_error() {
+        let endpoints = endpoints(EndpointStrategy::Failover);
+
+        let mut attempts = 0;
+        let result = endpoints.send(|_| {
+            attempts += 1;
+            refuse()
+        });
+        assert!(matches!(result, Err(Error::Http(HttpError::Connection(_)))));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_no_failover_after_request_was_sent() {
+        let endpoints = endpoints(EndpointStrategy::Failover);
+
+        for error in [
+            HttpError::Timeout,
+            HttpError::Interrupted("connection reset".to_string()),
+        ] {
+            let mut attempts = 0;
+            let mut error = Some(error);
+            let result = endpoints.send(|_| {
+                attempts += 1;
+                Err(error.take().unwrap().into())
+            });
+            assert!(result.is_err());
+            assert_eq!(attempts, 1);
+        }
+        assert!(endpoints.status().iter().all(|status| status.healthy));
+    }
+
+    #[test]
+    fn test_round_robin_rotates() {
+        let endpoints = endpoints(EndpointStrategy::RoundRobin);
+
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            endpoints
+                .send(|base| {
+                    seen.push(base.to_string());
+                    ok()
+                })
+                .unwrap();
+        }
+        assert_eq!(seen, ["http://a", "http://b", "http://c"]);
+    }
+
+    #[test]
+    fn test_least_latency_prefers_fastest() {
+        let endpoints = endpoints(EndpointStrategy::LeastLatency);
+        endpoints.entries[0].record_latency(Duration::from_millis(30));
+        endpoints.entries[1].record_latency(Duration::from_millis(5));
+        endpoints.entries[2].record_latency(Duration::from_millis(10));
+
+        assert_eq!(endpoints.order(), [1, 2, 0]);
+    }
+
+    #[test]
+    fn test_recovery_probe() {
+        let config = ClientConfig::new("http://a")
+            .with_endpoints(["http://b"])
+            .with_recovery_interval(Duration::ZERO);
+        let endpoints = Endpoints::new(&config);
+
+        endpoints
+            .send(|base| if base == "http://a" { refuse() } else { ok() })
+            .unwrap();
+        assert!(!endpoints.status()[0].healthy);
+
+        // The recovery interval has passed, so the primary is probed first.
+        let mut seen = Vec::new();
+        endpoints
+            .send(|base| {
+                seen.push(base.to_string());
+                ok()
+            })
+            .unwrap();
+        assert_eq!(seen, ["http://a"]);
+        assert!(endpoints.status()[0].healthy);
+    }
+}
@@ -1,11 +1,18 @@
 //! Error types for the `IDBuilder` SDK.
 
 use std::fmt;
+use std::time::Duration;
+
+use crate::http::Response;
 
 /// Result type alias using [`Error`].
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Errors that can occur when using the `IDBuilder` SDK.
+///
+/// Server error codes with a dedicated variant are mapped to it; any other
+/// non-zero code is returned as [`Error::Api`]. Use [`Error::kind`] or
+/// [`Error::is_retryable`] to handle errors by category.
 #[derive(Debug)]
 pub enum Error {
     /// HTTP transport error.
@@ -29,11 +36,25 @@ pub enum Error {
     Forbidden,
 
     /// Rate limit exceeded.
-    RateLimited,
+    RateLimited(RateLimit),
 
-    /// Sequence exhausted for the given key.
+    /// Sequence exhausted for the given key (server code `40901`).
     SequenceExhausted(String),
 
+    /// The given key is disabled (server code `40301`).
+    KeyDisabled(String),
+
+    /// The requested batch size was rejected (server code `40001`), with the
+    /// server's message.
+    InvalidSize(String),
+
+    /// The ID quota of the given key is used up (server code `42901`).
+    QuotaExceeded(String),
+
+    /// The server is down for maintenance (server code `50301`), with the
+    /// server's message.
+    Maintenance(String),
+
     /// Invalid client configuration.
     InvalidConfig(String),
 
@@ -43,6 +64,12 @@ pub enum Error {
     /// Sequence overflow (snowflake generation).
     SequenceOverflow,
 
+    /// The snowflake worker ID lease for the given key was lost.
+    LeaseLost(String),
+
+    /// Timed out waiting for pooled IDs for the given key.
+    PoolTimeout(String),
+
     /// JSON serialization/deserialization error.
     Serialization(serde_json::Error),
 
@@ -58,17 +85,173 @@ impl fmt::Display for Error {
             Self::ConfigNotFound(key) => write!(f, "Configuration not found: {key}"),
             Self::Unauthorized => write!(f, "Unauthorized: invalid or missing token"),
             Self::Forbidden => write!(f, "Forbidden: token not allowed for this operation"),
-            Self::RateLimited => write!(f, "Rate limited"),
+            Self::RateLimited(limit) => {
+                write!(f, "Rate limited")?;
+                if let Some(retry_after) = limit.retry_after {
+                    write!(f, ", retry after {retry_after:?}")?;
+                }
+                Ok(())
+            }
             Self::SequenceExhausted(key) => write!(f, "Sequence exhausted for key: {key}"),
+            Self::KeyDisabled(key) => write!(f, "Key is disabled: {key}"),
+            Self::InvalidSize(msg) => write!(f, "Invalid batch size: {msg}"),
+            Self::QuotaExceeded(key) => write!(f, "Quota exceeded for key: {key}"),
+            Self::Maintenance(msg) => write!(f, "Server under maintenance: {msg}"),
             Self::InvalidConfig(msg) => write!(f, "Invalid configuration: {msg}"),
             Self::ClockMovedBackwards => write!(f, "Snowflake clock moved backwards"),
             Self::SequenceOverflow => write!(f, "Snowflake sequence overflow"),
+            Self::LeaseLost(key) => write!(f, "Snowflake worker lease lost for key: {key}"),
+            Self::PoolTimeout(key) => write!(f, "Timed out waiting for pooled IDs for key: {key}"),
             Self::Serialization(e) => write!(f, "Serialization error: {e}"),
             Self::InvalidUrl(url) => write!(f, "Invalid URL: {url}"),
         }
     }
 }
 
+/// Error codes sent by the server in `ApiResponse::code`.
+///
+/// Codes are the HTTP status followed by two digits. The client maps codes
+/// without a dedicated [`Error`] variant by their HTTP status; those are only
+/// used to build responses in the fake server.
+#[cfg_attr(not(feature = "testing"), allow(dead_code))]
+pub mod code {
+    pub const INVALID_REQUEST: i32 = 40000;
+    pub const INVALID_SIZE: i32 = 40001;
+    pub const UNAUTHORIZED: i32 = 40100;
+    pub const FORBIDDEN: i32 = 40300;
+    pub const KEY_DISABLED: i32 = 40301;
+    pub const NOT_FOUND: i32 = 40401;
+    pub const SEQUENCE_EXHAUSTED: i32 = 40901;
+    pub const ALREADY_EXISTS: i32 = 40902;
+    pub const QUOTA_EXCEEDED: i32 = 42901;
+    pub const MAINTENANCE: i32 = 50301;
+}
+
+/// Category of an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    /// The request failed in transport or no complete response arrived.
+    Transport,
+
+    /// The token is missing, invalid or not allowed to do this.
+    Auth,
+
+    /// The key or resource does not exist.
+    NotFound,
+
+    /// The server rejected the request as invalid.
+    InvalidRequest,
+
+    /// The key is disabled.
+    KeyDisabled,
+
+    /// The key cannot issue more IDs.
+    Exhausted,
+
+    /// Too many requests in the current window.
+    RateLimited,
+
+    /// The ID quota of the key is used up.
+    QuotaExceeded,
+
+    /// The server is temporarily unavailable, e.g. for maintenance.
+    Unavailable,
+
+    /// The server failed to handle the request.
+    Server,
+
+    /// The response could not be decoded.
+    Decode,
+
+    /// The error was raised by the SDK itself, without a server response.
+    Local,
+}
+
+impl ErrorKind {
+    /// Categorize an HTTP status code.
+    const fn from_status(status: i32) -> Self {
+        match status {
+            401 | 403 => Self::Auth,
+            404 => Self::NotFound,
+            429 => Self::RateLimited,
+            503 => Self::Unavailable,
+            400..=499 => Self::InvalidRequest,
+            _ => Self::Server,
+        }
+    }
+}
+
+impl Error {
+    /// Map a non-zero server error code for the given key.
+    pub(crate) fn from_api(key: &str, code: i32, message: String) -> Self {
+        match code {
+            code::INVALID_SIZE => Self::InvalidSize(message),
+            code::KEY_DISABLED => Self::KeyDisabled(key.to_string()),
+            code::SEQUENCE_EXHAUSTED => Self::SequenceExhausted(key.to_string()),
+            code::QUOTA_EXCEEDED => Self::QuotaExceeded(key.to_string()),
+            code::MAINTENANCE => Self::Maintenance(message),
+            _ => Self::Api { code, message },
+        }
+    }
+
+    /// Get the category of the error.
+    ///
+    /// [`Error::Api`] is categorized by the HTTP status its code starts with.
+    #[must_use]
+    pub const fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Http(_) => ErrorKind::Transport,
+            Self::Api { code, .. } => {
+                let status = if *code >= 10000 { *code / 100 } else { *code };
+                ErrorKind::from_status(status)
+            }
+            Self::ConfigNotFound(_) => ErrorKind::NotFound,
+            Self::Unauthorized | Self::Forbidden => ErrorKind::Auth,
+            Self::RateLimited(_) => ErrorKind::RateLimited,
+            Self::SequenceExhausted(_) => ErrorKind::Exhausted,
+            Self::KeyDisabled(_) => ErrorKind::KeyDisabled,
+            Self::InvalidSize(_) => ErrorKind::InvalidRequest,
+            Self::QuotaExceeded(_) => ErrorKind::QuotaExceeded,
+            Self::Maintenance(_) => ErrorKind::Unavailable,
+            Self::Serialization(_) => ErrorKind::Decode,
+            Self::InvalidConfig(_)
+            | Self::ClockMovedBackwards
+            | Self::SequenceOverflow
+            | Self::LeaseLost(_)
+            | Self::PoolTimeout(_)
+            | Self::InvalidUrl(_) => ErrorKind::Local,
+        }
+    }
+
+    /// Whether the same operation may succeed if repeated later.
+    ///
+    /// This does not consider whether repeating is safe: a timed out ID
+    /// allocation may have been processed by the server.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        match self {
+            Self::Http(err) => !matches!(err, HttpError::Other(_)),
+            Self::ClockMovedBackwards | Self::SequenceOverflow | Self::PoolTimeout(_) => true,
+            _ => matches!(
+                self.kind(),
+                ErrorKind::RateLimited | ErrorKind::Unavailable | ErrorKind::Server
+            ),
+        }
+    }
+
+    /// Get the delay the server asked for before the next attempt.
+    ///
+    /// Only [`Error::RateLimited`] carries one, and only when the server sent
+    /// a `Retry-After` header.
+    #[must_use]
+    pub const fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited(limit) => limit.retry_after,
+            _ => None,
+        }
+    }
+}
+
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
@@ -91,15 +274,47 @@ impl From<HttpError> for Error {
     }
 }
 
+/// Rate limit details reported with HTTP 429.
+///
+/// Each field is `None` when the server did not send the matching header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Delay requested by the `Retry-After` header.
+    pub retry_after: Option<Duration>,
+
+    /// Requests left in the current window (`X-RateLimit-Remaining`).
+    pub remaining: Option<u64>,
+
+    /// Requests allowed per window (`X-RateLimit-Limit`).
+    pub limit: Option<u64>,
+}
+
+impl RateLimit {
+    /// Read the rate limit headers of a response.
+    #[must_use]
+    pub fn from_response(response: &Response) -> Self {
+        let number = |name: &str| response.header(name)?.trim().parse().ok();
+        Self {
+            retry_after: response.retry_after(),
+            remaining: number("X-RateLimit-Remaining"),
+            limit: number("X-RateLimit-Limit"),
+        }
+    }
+}
+
 /// HTTP transport errors.
 #[derive(Debug)]
 pub enum HttpError {
-    /// Connection failed.
+    /// The connection could not be established, so the request was not sent.
     Connection(String),
 
     /// Request timed out.
     Timeout,
 
+    /// The connection broke after the request was sent, so the server may
+    /// have processed it.
+    Interrupted(String),
+
     /// Failed to read response body.
     ResponseBody(String),
 
@@ -112,6 +327,7 @@ impl fmt::Display for HttpError {
         match self {
             Self::Connection(msg) => write!(f, "Connection failed: {msg}"),
             Self::Timeout => write!(f, "Request timed out"),
+            Self::Interrupted(msg) => write!(f, "Connection interrupted: {msg}"),
             Self::ResponseBody(msg) => write!(f, "Failed to read response body: {msg}"),
             Self::Other(msg) => write!(f, "{msg}"),
         }
@@ -119,3 +335,65 @@ impl fmt::Display for HttpError {
 }
 
 impl std::error::Error for HttpError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let response = Response::new(429, String::new())
+            .with_header("retry-after", "3")
+            .with_header("X-RateLimit-Remaining", "0")
+            .with_header("X-RateLimit-Limit", "500");
+        let limit = RateLimit::from_response(&response);
+
+        assert_eq!(limit.retry_after, Some(Duration::from_secs(3)));
+        assert_eq!(limit.remaining, Some(0));
+        assert_eq!(limit.limit, Some(500));
+
+        let err = Error::RateLimited(limit);
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(3)));
+        assert_eq!(err.to_string(), "Rate limited, retry after 3s");
+    }
+
+    #[test]
+    fn test_server_codes_map_to_variants() {
+        let err = Error::from_api("order-id", 40901, "sequence exhausted".to_string());
+        assert!(matches!(&err, Error::SequenceExhausted(key) if key == "order-id"));
+        assert_eq!(err.kind(), ErrorKind::Exhausted);
+        assert!(!err.is_retryable());
+
+        let err = Error::from_api("order-id", 50301, "back at 3:00".to_string());
+        assert!(matches!(&err, Error::Maintenance(msg) if msg == "back at 3:00"));
+        assert!(err.is_retryable());
+
+        let err = Error::from_api("order-id", 40099, "exhausted".to_string());
+        assert!(matches!(err, Error::Api { code: 40099, .. }));
+        assert_eq!(err.kind(), ErrorKind::InvalidRequest);
+    }
+
+    #[test]
+    fn test_api_kind_from_status() {
+        let api = |code| Error::Api {
+            code,
+            message: String::new(),
+        };
+        assert_eq!(api(502).kind(), ErrorKind::Server);
+        assert!(api(502).is_retryable());
+        assert_eq!(api(40402).kind(), ErrorKind::NotFound);
+        assert_eq!(api(1).kind(), ErrorKind::Server);
+        assert!(!Error::Http(HttpError::Other("bad scheme".to_string())).is_retryable());
+        assert!(Error::Http(HttpError::Timeout).is_retryable());
+    }
+
+    #[test]
+    fn test_rate_limit_without_headers() {
+        let response = Response::new(429, String::new())
+            .with_header("Retry-After", "Wed, 21 Oct 2015 07:28:00 GMT");
+        let err = Error::RateLimited(RateLimit::from_response(&response));
+
+        assert_eq!(err.retry_after(), None);
+        assert_eq!(err.to_string(), "Rate limited");
+    }
+}
@@ -0,0 +1,343 @@
+//! Owned API handles for a single key.
+//!
+//! The borrowed API types such as [`IncrementApi`] are meant to be used in
+//! place. The handles here own a client clone and the key instead, so they
+//! are `'static` when the HTTP client is: build one at startup, store it in
+//! a struct, clone it, or move it into a spawned task.
+
+use std::marker::PhantomData;
+
+use crate::api::{FormattedApi, IncrementApi, SnowflakeApi};
+use crate::client::IdBuilderClient;
+#[cfg(feature = "async")]
+use crate::http::AsyncHttpTransport;
+use crate::http::HttpClient;
+use crate::types::admin::{FormattedKeyConfig, IncrementKeyConfig, SnowflakeKeyConfig};
+use crate::types::response::SnowflakeIdResponse;
+use crate::Result;
+
+/// Owned handle to the API of one key.
+///
+/// The kind of key is the key configuration type, as in
+/// [`KeyAdminApi`](crate::api::KeyAdminApi): use the [`IncrementHandle`],
+/// [`SnowflakeHandle`] and [`FormattedHandle`] aliases.
+#[derive(Debug)]
+pub struct KeyHandle<C, K> {
+    client: IdBuilderClient<C>,
+    key: String,
+    key_token: Option<String>,
+    kind: PhantomData<K>,
+}
+
+/// Owned handle to the auto-increment API of one key.
+///
+/// Created with [`IdBuilderClient::increment_handle`].
+pub type IncrementHandle<C> = KeyHandle<C, IncrementKeyConfig>;
+
+/// Owned handle to the snowflake API of one key.
+///
+/// Created with [`IdBuilderClient::snowflake_handle`].
+pub type SnowflakeHandle<C> = KeyHandle<C, SnowflakeKeyConfig>;
+
+/// Owned handle to the formatted ID API of one key.
+///
+/// Created with [`IdBuilderClient::formatted_handle`].
+pub type FormattedHandle<C> = KeyHandle<C, FormattedKeyConfig>;
+
+impl<C, K> KeyHandle<C, K> {
+    pub(crate) const fn new(client: IdBuilderClient<C>, key: String) -> Self {
+        Self {
+            client,
+            key,
+            key_token: None,
+            kind: PhantomData,
+        }
+    }
+
+    /// Authorize requests with the given key token instead of the client's.
+    #[must_use]
+    pub fn with_key_token(mut self, key_token: impl Into<String>) -> Self {
+        self.key_token = Some(key_token.into());
+        self
+    }
+
+    /// Get the key.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the client.
+    #[must_use]
+    pub const fn client(&self) -> &IdBuilderClient<C> {
+        &self.client
+    }
+}
+
+impl<C, K> Clone for KeyHandle<C, K> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            key: self.key.clone(),
+            key_token: self.key_token.clone(),
+            kind: PhantomData,
+        }
+    }
+}
+
+impl<C> IncrementHandle<C> {
+    /// Borrow the API for this key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`](crate::Error::InvalidConfig) if
+    /// neither the handle nor the client has a key token.
+    pub fn api(&self) -> Result<IncrementApi<'_, C>> {
+        if let Some(key_token) = &self.key_token {
+            return Ok(self.client.increment_with_token(&self.key, key_token));
+        }
+        self.client.try_increment(&self.key)
+    }
+}
+
+impl<C: HttpClient> IncrementHandle<C> {
+    /// Generate a single auto-increment ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub fn generate_one(&self) -> Result<i64> {
+        self.api()?.generate_one()
+    }
+
+    /// Generate multiple auto-increment IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub fn generate(&self, count: u32) -> Result<Vec<i64>> {
+        self.api()?.generate(count)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> IncrementHandle<C> {
+    /// Generate a single auto-increment ID asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub async fn generate_one_async(&self) -> Result<i64> {
+        self.api()?.generate_one_async().await
+    }
+
+    /// Generate multiple auto-increment IDs asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub async fn generate_async(&self, count: u32) -> Result<Vec<i64>> {
+        self.api()?.generate_async(count).await
+    }
+}
+
+impl<C> SnowflakeHandle<C> {
+    /// Borrow the API for this key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`](crate::Error::InvalidConfig) if
+    /// neither the handle nor the client has a key token.
+    pub fn api(&self) -> Result<SnowflakeApi<'_, C>> {
+        if let Some(key_token) = &self.key_token {
+            return Ok(self.client.snowflake_with_token(&self.key, key_token));
+        }
+        self.client.try_snowflake(&self.key)
+    }
+}
+
+impl<C: HttpClient> SnowflakeHandle<C> {
+    /// Get the snowflake configuration for local ID generation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the configuration doesn't exist.
+    pub fn get_config(&self) -> Result<SnowflakeIdResponse> {
+        self.api()?.get_config()
+    }
+
+    /// Renew the lease on a worker ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LeaseLost`](crate::Error::LeaseLost) if the server no
+    /// longer holds the worker ID for this client, or an error if the request
+    /// fails.
+    pub fn heartbeat(&self, worker_id: u32) -> Result<()> {
+        self.api()?.heartbeat(worker_id)
+    }
+
+    /// Release a worker ID so the server can reassign it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub fn release(&self, worker_id: u32) -> Result<()> {
+        self.api()?.release(worker_id)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> SnowflakeHandle<C> {
+    /// Get the snowflake configuration for local ID generation asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the configuration doesn't exist.
+    pub async fn get_config_async(&self) -> Result<SnowflakeIdResponse> {
+        self.api()?.get_config_async().await
+    }
+
+    /// Renew the lease on a worker ID asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LeaseLost`](crate::Error::LeaseLost) if the server no
+    /// longer holds the worker ID for this client, or an error if the request
+    /// fails.
+    pub async fn heartbeat_async(&self, worker_id: u32) -> Result<()> {
+        self.api()?.heartbeat_async(worker_id).await
+    }
+
+    /// Release a worker ID asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn release_async(&self, worker_id: u32) -> Result<()> {
+        self.api()?.release_async(worker_id).await
+    }
+}
+
+impl<C> FormattedHandle<C> {
+    /// Borrow the API for this key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`](crate::Error::InvalidConfig) if
+    /// neither the handle nor the client has a key token.
+    pub fn api(&self) -> Result<FormattedApi<'_, C>> {
+        if let Some(key_token) = &self.key_token {
+            return Ok(self.client.formatted_with_token(&self.key, key_token));
+        }
+        self.client.try_formatted(&self.key)
+    }
+}
+
+impl<C: HttpClient> FormattedHandle<C> {
+    /// Generate a single formatted ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub fn generate_one(&self) -> Result<String> {
+        self.api()?.generate_one()
+    }
+
+    /// Generate multiple formatted IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub fn generate(&self, count: u32) -> Result<Vec<String>> {
+        self.api()?.generate(count)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> FormattedHandle<C> {
+    /// Generate a single formatted ID asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub async fn generate_one_async(&self) -> Result<String> {
+        self.api()?.generate_one_async().await
+    }
+
+    /// Generate multiple formatted IDs asynchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the sequence is exhausted.
+    pub async fn generate_async(&self, count: u32) -> Result<Vec<String>> {
+        self.api()?.generate_async(count).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::config::ClientConfig;
+    use crate::http::Response;
+
+    struct MockHttpClient;
+
+    impl HttpClient for MockHttpClient {
+        fn get(&self, _url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+            let body = if headers.contains(&("Authorization", "tenant-token")) {
+                r#"{"code":0,"message":"success","data":{"ids":[7]}}"#
+            } else {
+                r#"{"code":0,"message":"success","data":{"ids":[1]}}"#
+            };
+            Ok(Response::new(200, body.to_string()))
+        }
+
+        fn post(&self, _url: &str, _headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+            Ok(Response::new(
+                200,
+                r#"{"code":0,"message":"success","data":null}"#.to_string(),
+            ))
+        }
+    }
+
+    fn client() -> IdBuilderClient<MockHttpClient> {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        IdBuilderClient::with_http_client(config, MockHttpClient)
+    }
+
+    #[test]
+    fn test_handle_moves_into_thread() {
+        let handle = client().increment_handle("order-id");
+        let worker = handle.clone();
+
+        let id = thread::spawn(move || worker.generate_one().unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(handle.key(), "order-id");
+    }
+
+    #[test]
+    fn test_handle_token_override() {
+        let increment = client()
+            .increment_handle("order-id")
+            .with_key_token("tenant-token");
+        assert_eq!(increment.generate_one().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_handle_without_token_is_error() {
+        let client = IdBuilderClient::with_http_client(
+            ClientConfig::new("http://localhost:8080"),
+            MockHttpClient,
+        );
+        let handle = client.snowflake_handle("user-id");
+        assert!(matches!(
+            handle.get_config(),
+            Err(crate::Error::InvalidConfig(_))
+        ));
+    }
+}
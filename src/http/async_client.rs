@@ -1,9 +1,10 @@
 //! Asynchronous HTTP client using reqwest.
 
+use std::future::Future;
 use std::time::Duration;
 
 use crate::error::HttpError;
-use crate::http::Response;
+use crate::http::{AsyncHttpTransport, Response};
 use crate::Result;
 
 /// Asynchronous HTTP client based on reqwest.
@@ -46,14 +47,19 @@ impl AsyncHttpClient {
             req = req.header(*key, *value);
         }
 
-        let resp = req.send().await.map_err(map_reqwest_error)?;
+        let resp = req.send().await.map_err(|e| map_reqwest_error(&e))?;
         let status = resp.status().as_u16();
+        let headers = response_headers(resp.headers());
         let body = resp
             .text()
             .await
             .map_err(|e| HttpError::ResponseBody(format!("Failed to read response body: {e}")))?;
 
-        Ok(Response::new(status, body))
+        Ok(Response {
+            status,
+            body,
+            headers,
+        })
     }
 
     /// Perform an async POST request with JSON body.
@@ -69,18 +75,52 @@ impl AsyncHttpClient {
         req = req.header("Content-Type", "application/json");
         req = req.body(body.to_string());
 
-        let resp = req.send().await.map_err(map_reqwest_error)?;
+        let resp = req.send().await.map_err(|e| map_reqwest_error(&e))?;
         let status = resp.status().as_u16();
+        let headers = response_headers(resp.headers());
         let body = resp
             .text()
             .await
             .map_err(|e| HttpError::ResponseBody(format!("Failed to read response body: {e}")))?;
 
-        Ok(Response::new(status, body))
+        Ok(Response {
+            status,
+            body,
+            headers,
+        })
     }
 }
 
-fn map_reqwest_error(err: reqwest::Error) -> HttpError {
+impl AsyncHttpTransport for AsyncHttpClient {
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<Response>> + Send {
+        Self::get(self, url, headers)
+    }
+
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> impl Future<Output = Result<Response>> + Send {
+        Self::post(self, url, headers, body)
+    }
+}
+
+fn response_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?.to_string();
+            Some((name.as_str().to_string(), value))
+        })
+        .collect()
+}
+
+fn map_reqwest_error(err: &reqwest::Error) -> HttpError {
     if err.is_timeout() {
         HttpError::Timeout
     } else if err.is_connect() {
@@ -95,3 +135,37 @@ impl Default for AsyncHttpClient {
         Self::with_default_timeout().expect("Failed to create default HTTP client")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::Error;
+
+    #[tokio::test]
+    async fn test_refused_connection_is_connection_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+
+        let client = AsyncHttpClient::new(Duration::from_secs(5)).unwrap();
+        assert!(matches!(
+            client.get(&url, &[]).await,
+            Err(Error::Http(HttpError::Connection(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_missing_response_is_timeout() {
+        // The connection is accepted by the kernel, but nobody answers.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let client = AsyncHttpClient::new(Duration::from_millis(100)).unwrap();
+        assert!(matches!(
+            client.post(&url, &[], "{}").await,
+            Err(Error::Http(HttpError::Timeout))
+        ));
+    }
+}
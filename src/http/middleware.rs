@@ -0,0 +1,509 @@
+//! Middleware layers wrapping an HTTP client.
+//!
+//! A [`LayeredClient`] runs a stack of [`Layer`]s around every request of the
+//! client it wraps: each layer may edit the [`Request`] before it is sent and
+//! inspect or replace the outcome afterwards. Layers run in the order they
+//! were added before the request and in reverse order after it.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "async")]
+use crate::http::AsyncHttpTransport;
+use crate::http::{HttpClient, Response};
+use crate::Result;
+
+/// HTTP method of a [`Request`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    /// GET request.
+    Get,
+
+    /// POST request with a JSON body.
+    Post,
+}
+
+/// Outgoing request as seen by a [`Layer`].
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// HTTP method.
+    pub method: Method,
+
+    /// Full request URL.
+    pub url: String,
+
+    /// Request headers as name/value pairs.
+    pub headers: Vec<(String, String)>,
+
+    /// JSON body of a POST request.
+    pub body: Option<String>,
+
+    sent_at: Option<Instant>,
+}
+
+impl Request {
+    fn new(method: Method, url: &str, headers: &[(&str, &str)], body: Option<&str>) -> Self {
+        Self {
+            method,
+            url: url.to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+                .collect(),
+            body: body.map(str::to_string),
+            sent_at: None,
+        }
+    }
+
+    /// Get the first value of a header, compared case-insensitively.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set a header, replacing any existing values.
+    pub fn set_header(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.headers
+            .retain(|(header, _)| !header.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+    }
+
+    /// Time since the request was handed to the wrapped client.
+    ///
+    /// Zero while layers prepare the request.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.sent_at
+            .map_or(Duration::ZERO, |sent_at| sent_at.elapsed())
+    }
+
+    fn header_refs(&self) -> Vec<(&str, &str)> {
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
+/// Hooks run around every request of a [`LayeredClient`].
+///
+/// Both hooks do nothing by default.
+pub trait Layer: Send + Sync {
+    /// Called before the request is sent.
+    fn before(&self, _request: &mut Request) {}
+
+    /// Called with the outcome of the request, which may be replaced.
+    fn after(&self, _request: &Request, _outcome: &mut Result<Response>) {}
+}
+
+/// HTTP client running a stack of [`Layer`]s around another client.
+///
+/// Implements [`HttpClient`] and, with the `async` feature,
+/// [`AsyncHttpTransport`] when the wrapped client does.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use idbuilder::http::{HeaderLayer, LayeredClient, RequestIdLayer, SyncHttpClient, UserAgentLayer};
+/// use idbuilder::{ClientConfig, IdBuilderClient};
+///
+/// let http = LayeredClient::new(SyncHttpClient::new(Duration::from_secs(5)))
+///     .layer(UserAgentLayer::new("billing/1.4"))
+///     .layer(HeaderLayer::new([("X-Tenant", "acme")]))
+///     .layer(RequestIdLayer::new());
+///
+/// let config = ClientConfig::new("http://localhost:8080").with_key_token("my-key-token");
+/// let client = IdBuilderClient::with_http_client(config, http);
+/// ```
+#[derive(Clone)]
+pub struct LayeredClient<C> {
+    inner: C,
+    layers: Vec<Arc<dyn Layer>>,
+}
+
+impl<C> LayeredClient<C> {
+    /// Wrap a client without any layers.
+    #[must_use]
+    pub const fn new(inner: C) -> Self {
+        Self {
+            inner,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Add a layer on top of the stack.
+    #[must_use]
+    pub fn layer(mut self, layer: impl Layer + 'static) -> Self {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    /// Get the wrapped client.
+    #[must_use]
+    pub const fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn prepare(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Option<&str>,
+    ) -> Request {
+        let mut request = Request::new(method, url, headers, body);
+        for layer in &self.layers {
+            layer.before(&mut request);
+        }
+        request.sent_at = Some(Instant::now());
+        request
+    }
+
+    fn finish(&self, request: &Request, outcome: &mut Result<Response>) {
+        for layer in self.layers.iter().rev() {
+            layer.after(request, outcome);
+        }
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for LayeredClient<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayeredClient")
+            .field("inner", &self.inner)
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+impl<C: HttpClient> HttpClient for LayeredClient<C> {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+        let request = self.prepare(Method::Get, url, headers, None);
+        let mut outcome = self.inner.get(&request.url, &request.header_refs());
+        self.finish(&request, &mut outcome);
+        outcome
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response> {
+        let request = self.prepare(Method::Post, url, headers, Some(body));
+        let body = request.body.as_deref().unwrap_or_default();
+        let mut outcome = self.inner.post(&request.url, &request.header_refs(), body);
+        self.finish(&request, &mut outcome);
+        outcome
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> AsyncHttpTransport for LayeredClient<C> {
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<Response>> + Send {
+        let request = self.prepare(Method::Get, url, headers, None);
+        async move {
+            let mut outcome = self.inner.get(&request.url, &request.header_refs()).await;
+            self.finish(&request, &mut outcome);
+            outcome
+        }
+    }
+
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> impl Future<Output = Result<Response>> + Send {
+        let request = self.prepare(Method::Post, url, headers, Some(body));
+        async move {
+            let body = request.body.as_deref().unwrap_or_default();
+            let mut outcome = self
+                .inner
+                .post(&request.url, &request.header_refs(), body)
+                .await;
+            self.finish(&request, &mut outcome);
+            outcome
+        }
+    }
+}
+
+/// Layer adding fixed headers to every request.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderLayer {
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderLayer {
+    /// Create a layer setting the given headers.
+    #[must_use]
+    pub fn new<I, N, V>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = (N, V)>,
+        N: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            headers: headers
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        }
+    }
+
+    /// Add a header.
+    #[must_use]
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+impl Layer for HeaderLayer {
+    fn before(&self, request: &mut Request) {
+        for (name, value) in &self.headers {
+            request.set_header(name.as_str(), value.as_str());
+        }
+    }
+}
+
+/// Layer setting the `User-Agent` header.
+#[derive(Debug, Clone)]
+pub struct UserAgentLayer {
+    user_agent: String,
+}
+
+impl UserAgentLayer {
+    /// Default user agent, `idbuilder-rust/<version>`.
+    pub const DEFAULT_USER_AGENT: &'static str =
+        concat!("idbuilder-rust/", env!("CARGO_PKG_VERSION"));
+
+    /// Create a layer sending the given user agent.
+    #[must_use]
+    pub fn new(user_agent: impl Into<String>) -> Self {
+        Self {
+            user_agent: user_agent.into(),
+        }
+    }
+}
+
+impl Default for UserAgentLayer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_USER_AGENT)
+    }
+}
+
+impl Layer for UserAgentLayer {
+    fn before(&self, request: &mut Request) {
+        request.set_header("User-Agent", self.user_agent.as_str());
+    }
+}
+
+/// Layer giving every request a unique ID header.
+///
+/// Requests that already carry the header keep their ID. Each attempt of a
+/// retried request is a separate request and gets its own ID.
+#[derive(Debug)]
+pub struct RequestIdLayer {
+    header: String,
+    prefix: u64,
+    counter: AtomicU64,
+}
+
+impl RequestIdLayer {
+    /// Default request ID header.
+    pub const DEFAULT_HEADER: &'static str = "X-Request-Id";
+
+    /// Create a layer setting the `X-Request-Id` header.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            header: Self::DEFAULT_HEADER.to_string(),
+            prefix: RandomState::new().build_hasher().finish(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the header the ID is sent in.
+    #[must_use]
+    pub fn with_header(mut self, header: impl Into<String>) -> Self {
+        self.header = header.into();
+        self
+    }
+
+    /// Generate the next request ID: a random per-layer prefix and a counter.
+    fn next_id(&self) -> String {
+        let count = self.counter.fetch_add(1, Ordering::Relaxed);
+        format!("{:016x}{count:016x}", self.prefix)
+    }
+}
+
+impl Default for RequestIdLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layer for RequestIdLayer {
+    fn before(&self, request: &mut Request) {
+        if request.header(&self.header).is_none() {
+            let id = self.next_id();
+            request.headers.push((self.header.clone(), id));
+        }
+    }
+}
+
+/// Callback receiving the timing of a request.
+type TimingCallback = dyn Fn(&Request, &Result<Response>, Duration) + Send + Sync;
+
+/// Layer reporting how long each request took.
+///
+/// # Example
+///
+/// ```
+/// use idbuilder::http::{LayeredClient, SyncHttpClient, TimingLayer};
+///
+/// let http = LayeredClient::new(SyncHttpClient::with_default_timeout()).layer(
+///     TimingLayer::new(|request, outcome, elapsed| {
+///         let status = outcome.as_ref().map(|response| response.status);
+///         eprintln!("{} -> {status:?} in {elapsed:?}", request.url);
+///     }),
+/// );
+/// ```
+pub struct TimingLayer {
+    callback: Box<TimingCallback>,
+}
+
+impl TimingLayer {
+    /// Create a layer calling `callback` after every request.
+    pub fn new(
+        callback: impl Fn(&Request, &Result<Response>, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            callback: Box::new(callback),
+        }
+    }
+}
+
+impl fmt::Debug for TimingLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimingLayer").finish_non_exhaustive()
+    }
+}
+
+impl Layer for TimingLayer {
+    fn after(&self, request: &Request, outcome: &mut Result<Response>) {
+        (self.callback)(request, outcome, request.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::Error;
+
+    /// Echoes the request headers back as the response body.
+    struct EchoHttpClient;
+
+    impl HttpClient for EchoHttpClient {
+        fn get(&self, _url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+            Ok(Response::new(200, format!("{headers:?}")))
+        }
+
+        fn post(&self, url: &str, headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+            self.get(url, headers)
+        }
+    }
+
+    /// Records the hook order into a shared log.
+    struct LogLayer(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Layer for LogLayer {
+        fn before(&self, _request: &mut Request) {
+            self.1.lock().unwrap().push(format!("before {}", self.0));
+        }
+
+        fn after(&self, _request: &Request, _outcome: &mut Result<Response>) {
+            self.1.lock().unwrap().push(format!("after {}", self.0));
+        }
+    }
+
+    #[test]
+    fn test_layers_run_in_stack_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let client = LayeredClient::new(EchoHttpClient)
+            .layer(LogLayer("outer", Arc::clone(&log)))
+            .layer(LogLayer("inner", Arc::clone(&log)));
+
+        client.get("http://localhost/", &[]).unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["before outer", "before inner", "after inner", "after outer"]
+        );
+    }
+
+    #[test]
+    fn test_builtin_layers_set_headers() {
+        let client = LayeredClient::new(EchoHttpClient)
+            .layer(UserAgentLayer::new("billing/1.4"))
+            .layer(HeaderLayer::new([("X-Tenant", "acme")]))
+            .layer(RequestIdLayer::new());
+
+        let body = client
+            .post("http://localhost/", &[("user-agent", "other")], "{}")
+            .unwrap()
+            .body;
+        assert!(body.contains(r#"("User-Agent", "billing/1.4")"#));
+        assert!(!body.contains("other"));
+        assert!(body.contains(r#"("X-Tenant", "acme")"#));
+        assert!(body.contains(r#"("X-Request-Id", ""#));
+    }
+
+    #[test]
+    fn test_request_ids_are_unique() {
+        let layer = RequestIdLayer::new();
+        assert_ne!(layer.next_id(), layer.next_id());
+    }
+
+    #[test]
+    fn test_after_hook_can_replace_outcome() {
+        struct Reject;
+
+        impl Layer for Reject {
+            fn after(&self, _request: &Request, outcome: &mut Result<Response>) {
+                *outcome = Err(Error::Forbidden);
+            }
+        }
+
+        let timings = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&timings);
+        let client = LayeredClient::new(EchoHttpClient)
+            .layer(TimingLayer::new(move |request, outcome, _elapsed| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((request.method, outcome.is_ok()));
+            }))
+            .layer(Reject);
+
+        assert!(matches!(
+            client.get("http://localhost/", &[]),
+            Err(Error::Forbidden)
+        ));
+        assert_eq!(*timings.lock().unwrap(), [(Method::Get, false)]);
+    }
+}
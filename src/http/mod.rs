@@ -1,5 +1,15 @@
 //! HTTP client abstraction layer.
 
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::time::Duration;
+
+mod middleware;
+
+pub use middleware::{
+    HeaderLayer, Layer, LayeredClient, Method, Request, RequestIdLayer, TimingLayer, UserAgentLayer,
+};
+
 #[cfg(feature = "sync")]
 mod sync_client;
 
@@ -19,13 +29,47 @@ pub struct Response {
     pub status: u16,
     /// Response body as string.
     pub body: String,
+    /// Response headers as name/value pairs, in the order received.
+    pub headers: Vec<(String, String)>,
 }
 
 impl Response {
-    /// Create a new response.
+    /// Create a new response without headers.
     #[must_use]
     pub const fn new(status: u16, body: String) -> Self {
-        Self { status, body }
+        Self {
+            status,
+            body,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Add a header.
+    #[must_use]
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Get the first value of a header, compared case-insensitively.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the delay requested by the `Retry-After` header.
+    ///
+    /// Only the delay-seconds form is understood; an HTTP date is ignored.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.header("Retry-After")?
+            .trim()
+            .parse()
+            .ok()
+            .map(Duration::from_secs)
     }
 
     /// Check if the response indicates success (2xx).
@@ -51,3 +95,33 @@ pub trait HttpClient {
     /// Returns an error if the request fails.
     fn post(&self, url: &str, headers: &[(&str, &str)], body: &str) -> crate::Result<Response>;
 }
+
+/// Trait for asynchronous HTTP client implementations.
+///
+/// This is the async counterpart of [`HttpClient`]. The returned futures must
+/// be `Send` so that requests can be driven from multi-threaded runtimes.
+#[cfg(feature = "async")]
+pub trait AsyncHttpTransport {
+    /// Perform an async GET request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = crate::Result<Response>> + Send;
+
+    /// Perform an async POST request with JSON body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> impl Future<Output = crate::Result<Response>> + Send;
+}
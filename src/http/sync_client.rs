@@ -41,14 +41,24 @@ impl HttpClient for SyncHttpClient {
         match req.call() {
             Ok(resp) => {
                 let status = resp.status();
+                let headers = response_headers(&resp);
                 let body = resp.into_string().map_err(|e| {
                     HttpError::ResponseBody(format!("Failed to read response body: {e}"))
                 })?;
-                Ok(Response::new(status, body))
+                Ok(Response {
+                    status,
+                    body,
+                    headers,
+                })
             }
             Err(ureq::Error::Status(status, resp)) => {
+                let headers = response_headers(&resp);
                 let body = resp.into_string().unwrap_or_default();
-                Ok(Response::new(status, body))
+                Ok(Response {
+                    status,
+                    body,
+                    headers,
+                })
             }
             Err(ureq::Error::Transport(e)) => Err(map_transport_error(&e).into()),
         }
@@ -64,25 +74,51 @@ impl HttpClient for SyncHttpClient {
         match req.send_string(body) {
             Ok(resp) => {
                 let status = resp.status();
+                let headers = response_headers(&resp);
                 let body = resp.into_string().map_err(|e| {
                     HttpError::ResponseBody(format!("Failed to read response body: {e}"))
                 })?;
-                Ok(Response::new(status, body))
+                Ok(Response {
+                    status,
+                    body,
+                    headers,
+                })
             }
             Err(ureq::Error::Status(status, resp)) => {
+                let headers = response_headers(&resp);
                 let body = resp.into_string().unwrap_or_default();
-                Ok(Response::new(status, body))
+                Ok(Response {
+                    status,
+                    body,
+                    headers,
+                })
             }
             Err(ureq::Error::Transport(e)) => Err(map_transport_error(&e).into()),
         }
     }
 }
 
+fn response_headers(resp: &ureq::Response) -> Vec<(String, String)> {
+    resp.headers_names()
+        .into_iter()
+        .filter_map(|name| {
+            let value = resp.header(&name)?.to_string();
+            Some((name, value))
+        })
+        .collect()
+}
+
+/// Map a transport error.
+///
+/// Only failures to connect are [`HttpError::Connection`]. I/O errors happen
+/// once the request is on its way, so the server may have processed it.
 fn map_transport_error(err: &ureq::Transport) -> HttpError {
     use ureq::ErrorKind;
 
     match err.kind() {
-        ErrorKind::Io | ErrorKind::ConnectionFailed => HttpError::Connection(err.to_string()),
+        ErrorKind::ConnectionFailed => HttpError::Connection(err.to_string()),
+        ErrorKind::Io if is_timeout(err) => HttpError::Timeout,
+        ErrorKind::Io => HttpError::Interrupted(err.to_string()),
         ErrorKind::TooManyRedirects => HttpError::Other("Too many redirects".to_string()),
         ErrorKind::UnknownScheme => HttpError::Other(format!("Unknown URL scheme: {err}")),
         ErrorKind::Dns => HttpError::Connection(format!("DNS resolution failed: {err}")),
@@ -96,6 +132,18 @@ fn map_transport_error(err: &ureq::Transport) -> HttpError {
     }
 }
 
+/// Check if a transport error was caused by an I/O timeout.
+fn is_timeout(err: &ureq::Transport) -> bool {
+    std::error::Error::source(err)
+        .and_then(|source| source.downcast_ref::<std::io::Error>())
+        .is_some_and(|err| {
+            matches!(
+                err.kind(),
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+            )
+        })
+}
+
 impl Default for SyncHttpClient {
     fn default() -> Self {
         Self::with_default_timeout()
@@ -104,7 +152,12 @@ impl Default for SyncHttpClient {
 
 #[cfg(test)]
 mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
     use super::*;
+    use crate::{ClientConfig, Error, IdBuilderClient};
 
     #[test]
     fn test_create_client() {
@@ -117,4 +170,32 @@ mod tests {
         let client = SyncHttpClient::default();
         assert!(std::mem::size_of_val(&client) > 0);
     }
+
+    #[test]
+    fn test_reset_after_send_is_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = listener.try_clone().unwrap();
+        let accepted = thread::spawn(move || {
+            // Read the request, then hang up without answering.
+            let (mut stream, _) = server.accept().unwrap();
+            let _ = stream.read(&mut [0; 4096]);
+        });
+
+        let config = ClientConfig::new(url)
+            .with_key_token("test-token")
+            .with_timeout(Duration::from_secs(2))
+            .with_retries(3);
+        let client =
+            IdBuilderClient::with_http_client(config.clone(), SyncHttpClient::new(config.timeout));
+        let result = client.increment("order-id").generate(1);
+        accepted.join().unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::Http(HttpError::Interrupted(_)))
+        ));
+        listener.set_nonblocking(true).unwrap();
+        assert!(listener.accept().is_err());
+    }
 }
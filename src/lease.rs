@@ -0,0 +1,412 @@
+//! Leased snowflake worker IDs.
+//!
+//! Worker IDs handed out by the server are leases: the client must renew them
+//! periodically and should release them when it shuts down, otherwise
+//! restarted processes leak worker IDs and a reassigned worker ID can collide
+//! with one still in use.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::http::HttpClient;
+use crate::types::response::SnowflakeIdResponse;
+use crate::{Error, IdBuilderClient, Result, SnowflakeGenerator};
+
+/// Options controlling renewal of a [`SnowflakeLease`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaseOptions {
+    /// Interval between heartbeats.
+    pub heartbeat_interval: Duration,
+
+    /// Time without a successful heartbeat after which the lease is
+    /// considered lost.
+    ///
+    /// This should be shorter than the server-side lease expiry so that
+    /// generation stops before the worker ID can be reassigned.
+    pub lease_timeout: Duration,
+
+    /// Release the worker ID when the lease is dropped.
+    pub release_on_drop: bool,
+}
+
+impl LeaseOptions {
+    /// Default heartbeat interval (10 seconds).
+    pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// Default lease timeout (30 seconds).
+    pub const DEFAULT_LEASE_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Create options with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            heartbeat_interval: Self::DEFAULT_HEARTBEAT_INTERVAL,
+            lease_timeout: Self::DEFAULT_LEASE_TIMEOUT,
+            release_on_drop: true,
+        }
+    }
+
+    /// Set the interval between heartbeats.
+    #[must_use]
+    pub const fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Set the time without a successful heartbeat after which the lease is lost.
+    #[must_use]
+    pub const fn with_lease_timeout(mut self, timeout: Duration) -> Self {
+        self.lease_timeout = timeout;
+        self
+    }
+
+    /// Set whether the worker ID is released when the lease is dropped.
+    #[must_use]
+    pub const fn with_release_on_drop(mut self, release: bool) -> Self {
+        self.release_on_drop = release;
+        self
+    }
+}
+
+impl Default for LeaseOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A snowflake worker ID held under a lease.
+///
+/// Acquiring a lease fetches the snowflake configuration and starts a
+/// background thread that renews the worker ID with heartbeats. IDs are
+/// generated locally as with a [`SnowflakeGenerator`], but generation fails
+/// with [`Error::LeaseLost`] once the lease has been lost, so that a worker ID
+/// the server may have reassigned is never used.
+///
+/// Dropping the lease stops the heartbeats and releases the worker ID.
+///
+/// # Example
+///
+/// ```no_run
+/// use idbuilder::{IdBuilderClient, LeaseOptions, Result, SnowflakeLease};
+///
+/// fn main() -> Result<()> {
+///     let client = IdBuilderClient::new("http://localhost:8080", "my-key-token")?;
+///     let lease = SnowflakeLease::acquire(client, "user-id", LeaseOptions::new())?;
+///
+///     let id = lease.next_id()?;
+///     println!("Snowflake ID: {}", id);
+///
+///     // Dropping the lease releases the worker ID
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SnowflakeLease<C: HttpClient> {
+    client: IdBuilderClient<C>,
+    key: String,
+    config: SnowflakeIdResponse,
+    generator: SnowflakeGenerator,
+    options: LeaseOptions,
+    state: Arc<LeaseState>,
+    stop: Option<Sender<()>>,
+    heartbeat: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+struct LeaseState {
+    lost: AtomicBool,
+    renewed_at: Mutex<Instant>,
+}
+
+impl LeaseState {
+    fn renewed(&self) {
+        *self
+            .renewed_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Instant::now();
+    }
+
+    fn is_valid(&self, timeout: Duration) -> bool {
+        !self.lost.load(Ordering::Acquire)
+            && self
+                .renewed_at
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .elapsed()
+                <= timeout
+    }
+}
+
+impl<C: HttpClient + Send + Sync + 'static> SnowflakeLease<C> {
+    /// Acquire a worker ID for the given key and start renewing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snowflake configuration cannot be fetched, or
+    /// [`Error::InvalidConfig`](crate::Error::InvalidConfig) if it does not
+    /// describe a valid layout and worker ID.
+    pub fn acquire(
+        client: IdBuilderClient<C>,
+        key: impl Into<String>,
+        options: LeaseOptions,
+    ) -> Result<Self> {
+        let key = key.into();
+        let api = client.try_snowflake(key.as_str())?;
+        let config = api.get_config()?;
+        let generator = match config.clone().try_into_generator() {
+            Ok(generator) => generator.with_metrics(key.as_str(), client.metrics().clone()),
+            Err(err) => {
+                // Do not hold on to a worker ID that cannot be used.
+                let _ = api.release(config.worker_id);
+                return Err(err);
+            }
+        };
+        let state = Arc::new(LeaseState {
+            lost: AtomicBool::new(false),
+            renewed_at: Mutex::new(Instant::now()),
+        });
+
+        let (stop, stopped) = mpsc::channel();
+        let heartbeat = {
+            let client = client.clone();
+            let state = Arc::clone(&state);
+            let key = key.clone();
+            let worker_id = config.worker_id;
+            let interval = options.heartbeat_interval;
+            thread::spawn(move || loop {
+                match stopped.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+                }
+                match client
+                    .try_snowflake(key.as_str())
+                    .and_then(|api| api.heartbeat(worker_id))
+                {
+                    Ok(()) => state.renewed(),
+                    Err(Error::LeaseLost(_) | Error::ConfigNotFound(_)) => {
+                        state.lost.store(true, Ordering::Release);
+                        return;
+                    }
+                    // Transient failure: try again at the next interval. The
+                    // lease times out if renewal keeps failing.
+                    Err(_) => {}
+                }
+            })
+        };
+
+        Ok(Self {
+            client,
+            key,
+            config,
+            generator,
+            options,
+            state,
+            stop: Some(stop),
+            heartbeat: Some(heartbeat),
+        })
+    }
+}
+
+impl<C: HttpClient> SnowflakeLease<C> {
+    /// Generate the next unique ID under this lease.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LeaseLost`] if the lease has been lost, or an error if
+    /// ID generation fails.
+    pub fn next_id(&self) -> Result<i64> {
+        self.check()?;
+        self.generator.next_id()
+    }
+
+    /// Generate multiple IDs under this lease.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LeaseLost`] if the lease has been lost, or an error if
+    /// ID generation fails.
+    pub fn next_ids(&self, count: usize) -> Result<Vec<i64>> {
+        self.check()?;
+        self.generator.next_ids(count)
+    }
+
+    /// Whether the lease is still held.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.state.is_valid(self.options.lease_timeout)
+    }
+
+    /// Get the leased worker ID.
+    #[must_use]
+    pub const fn worker_id(&self) -> u32 {
+        self.config.worker_id
+    }
+
+    /// Get the snowflake configuration this lease was acquired with.
+    #[must_use]
+    pub const fn config(&self) -> &SnowflakeIdResponse {
+        &self.config
+    }
+
+    /// Get the key this lease belongs to.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Stop renewing the lease and release the worker ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the release request fails.
+    pub fn release(mut self) -> Result<()> {
+        self.stop_heartbeat();
+        // Prevent a second release when `self` is dropped.
+        self.options.release_on_drop = false;
+        if self.state.lost.load(Ordering::Acquire) {
+            return Err(Error::LeaseLost(self.key.clone()));
+        }
+        self.client
+            .try_snowflake(self.key.as_str())?
+            .release(self.config.worker_id)
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.is_valid() {
+            Ok(())
+        } else {
+            Err(Error::LeaseLost(self.key.clone()))
+        }
+    }
+
+    fn stop_heartbeat(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.heartbeat.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl<C: HttpClient> Drop for SnowflakeLease<C> {
+    fn drop(&mut self) {
+        self.stop_heartbeat();
+        if self.options.release_on_drop && !self.state.lost.load(Ordering::Acquire) {
+            // Errors cannot be reported from `drop`; an unreleased worker ID
+            // expires on the server once heartbeats stop.
+            let _ = self
+                .client
+                .try_snowflake(self.key.as_str())
+                .and_then(|api| api.release(self.config.worker_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Response;
+    use crate::ClientConfig;
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Default)]
+    struct LeaseHttpClient {
+        heartbeats: AtomicUsize,
+        releases: AtomicUsize,
+        lost: AtomicBool,
+    }
+
+    impl HttpClient for LeaseHttpClient {
+        fn get(&self, _url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+            Ok(Response::new(
+                200,
+                r#"{"code":0,"message":"success","data":{"worker_id":7,"epoch":1704067200000,"worker_bits":10,"sequence_bits":12}}"#
+                    .to_string(),
+            ))
+        }
+
+        fn post(&self, url: &str, _headers: &[(&str, &str)], body: &str) -> Result<Response> {
+            assert_eq!(body, r#"{"key":"user-id","worker_id":7}"#);
+            if url.ends_with("/heartbeat") {
+                self.heartbeats.fetch_add(1, Ordering::SeqCst);
+                if self.lost.load(Ordering::SeqCst) {
+                    return Ok(Response::new(410, String::new()));
+                }
+            } else {
+                assert!(url.ends_with("/release"));
+                self.releases.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(Response::new(
+                200,
+                r#"{"code":0,"message":"success","data":null}"#.to_string(),
+            ))
+        }
+    }
+
+    fn client() -> IdBuilderClient<LeaseHttpClient> {
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        IdBuilderClient::with_http_client(config, LeaseHttpClient::default())
+    }
+
+    fn fast_options() -> LeaseOptions {
+        LeaseOptions::new().with_heartbeat_interval(Duration::from_millis(5))
+    }
+
+    fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_heartbeats_and_release_on_drop() {
+        let client = client();
+        let lease = SnowflakeLease::acquire(client.clone(), "user-id", fast_options()).unwrap();
+        assert_eq!(lease.worker_id(), 7);
+        assert!(lease.next_id().is_ok());
+
+        wait_for(|| client.http_client().heartbeats.load(Ordering::SeqCst) >= 2);
+        assert!(lease.is_valid());
+
+        drop(lease);
+        assert_eq!(client.http_client().releases.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_explicit_release_only_once() {
+        let client = client();
+        let lease =
+            SnowflakeLease::acquire(client.clone(), "user-id", LeaseOptions::new()).unwrap();
+
+        lease.release().unwrap();
+        assert_eq!(client.http_client().releases.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_lost_lease_stops_generation() {
+        let client = client();
+        client.http_client().lost.store(true, Ordering::SeqCst);
+        let lease = SnowflakeLease::acquire(client.clone(), "user-id", fast_options()).unwrap();
+
+        wait_for(|| !lease.is_valid());
+        assert!(matches!(lease.next_id(), Err(Error::LeaseLost(key)) if key == "user-id"));
+
+        drop(lease);
+        assert_eq!(client.http_client().releases.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_lease_times_out_without_heartbeats() {
+        let options = LeaseOptions::new()
+            .with_heartbeat_interval(Duration::from_secs(60))
+            .with_lease_timeout(Duration::ZERO);
+        let lease = SnowflakeLease::acquire(client(), "user-id", options).unwrap();
+
+        thread::sleep(Duration::from_millis(2));
+        assert!(!lease.is_valid());
+        assert!(matches!(lease.next_id(), Err(Error::LeaseLost(_))));
+    }
+}
@@ -86,17 +86,43 @@
 
 #![warn(missing_docs)]
 
+mod buffer;
 mod client;
+mod clock;
 mod config;
+mod endpoint;
 mod error;
+mod handle;
+mod lease;
+mod metrics;
+mod retry;
 mod snowflake;
+mod trace;
 
 pub mod api;
 pub mod http;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod types;
 
+pub use buffer::{BufferOptions, BufferedIncrement, FormattedIdPool, PoolOptions};
 pub use client::IdBuilderClient;
-pub use config::ClientConfig;
-pub use error::{Error, Result};
-pub use snowflake::SnowflakeGenerator;
+pub use clock::{Clock, MockClock, MonotonicClock, SystemClock};
+pub use config::{ClientConfig, ClientConfigBuilder, ClientProfiles, ClientSettings};
+pub use endpoint::{EndpointStatus, EndpointStrategy};
+pub use error::{Error, ErrorKind, RateLimit, Result};
+pub use handle::{FormattedHandle, IncrementHandle, KeyHandle, SnowflakeHandle};
+pub use lease::{LeaseOptions, SnowflakeLease};
+#[cfg(feature = "metrics")]
+pub use metrics::MetricsFacade;
+pub use metrics::{
+    Counter, Histogram, HistogramSummary, Metrics, MetricsRecorder, MetricsSink, MetricsSnapshot,
+};
+pub use retry::{RetryClass, RetryPolicy};
+pub use snowflake::{
+    ClockRollbackPolicy, RollbackStats, SnowflakeGenerator, SnowflakeId, SnowflakeLayout,
+    SnowflakeParts,
+};
+pub use types::admin::{FormattedKeyConfig, IncrementKeyConfig, KeyConfig, SnowflakeKeyConfig};
 pub use types::response::{ApiResponse, SnowflakeIdResponse};
+pub use types::token::{IssueTokenRequest, IssuedToken, TokenInfo};
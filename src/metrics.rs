@@ -0,0 +1,430 @@
+//! Metrics for ID generation.
+//!
+//! The API layer, the local buffers and snowflake generators report counters
+//! and histograms, labelled by key, to a [`MetricsSink`]:
+//!
+//! - [`MetricsRecorder`] keeps them in memory and hands out a
+//!   [`MetricsSnapshot`], for apps without a metrics facade
+//! - [`MetricsFacade`] forwards them to the `metrics` crate (`metrics`
+//!   feature)
+//!
+//! Metrics are off unless a sink is configured, see
+//! [`ClientConfig::with_metrics`](crate::ClientConfig::with_metrics) and
+//! [`SnowflakeGenerator::with_metrics`](crate::SnowflakeGenerator::with_metrics).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+use crate::{ErrorKind, Result};
+
+/// A counter reported to a [`MetricsSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Counter {
+    /// IDs issued, by the server for API calls or locally by a snowflake
+    /// generator.
+    IdsIssued,
+
+    /// Calls to the server API, successful or not.
+    Calls,
+
+    /// Failed calls to the server API, by error kind.
+    Errors(ErrorKind),
+
+    /// Segments or batches fetched by a local buffer.
+    BufferRefills,
+
+    /// Times a snowflake generator waited for the next millisecond because
+    /// the sequence was used up.
+    OverflowWaits,
+
+    /// Times a snowflake generator saw the clock move backwards. Under
+    /// [`ClockRollbackPolicy::Logical`](crate::ClockRollbackPolicy::Logical)
+    /// this counts every ID issued while the clock is behind.
+    ClockRollbacks,
+}
+
+impl Counter {
+    /// Get the exported name of the counter.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::IdsIssued => "idbuilder_ids_issued_total",
+            Self::Calls => "idbuilder_calls_total",
+            Self::Errors(_) => "idbuilder_errors_total",
+            Self::BufferRefills => "idbuilder_buffer_refills_total",
+            Self::OverflowWaits => "idbuilder_overflow_waits_total",
+            Self::ClockRollbacks => "idbuilder_clock_rollbacks_total",
+        }
+    }
+}
+
+/// A histogram reported to a [`MetricsSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Histogram {
+    /// Duration of a call to the server API in seconds, including retries.
+    CallDuration,
+
+    /// Number of IDs fetched by a buffer refill.
+    RefillSize,
+
+    /// Time a snowflake generator waited for the next millisecond in seconds.
+    OverflowWait,
+}
+
+impl Histogram {
+    /// Get the exported name of the histogram.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::CallDuration => "idbuilder_call_duration_seconds",
+            Self::RefillSize => "idbuilder_buffer_refill_size",
+            Self::OverflowWait => "idbuilder_overflow_wait_seconds",
+        }
+    }
+}
+
+/// Destination of the metrics reported by the SDK.
+///
+/// Every value is labelled with the key it belongs to.
+pub trait MetricsSink: Send + Sync {
+    /// Add `value` to `counter` for `key`.
+    fn increment(&self, counter: Counter, key: &str, value: u64);
+
+    /// Record a `value` in `histogram` for `key`.
+    fn record(&self, histogram: Histogram, key: &str, value: f64);
+}
+
+impl<T: MetricsSink + ?Sized> MetricsSink for Arc<T> {
+    fn increment(&self, counter: Counter, key: &str, value: u64) {
+        (**self).increment(counter, key, value);
+    }
+
+    fn record(&self, histogram: Histogram, key: &str, value: f64) {
+        (**self).record(histogram, key, value);
+    }
+}
+
+/// Shared handle to a [`MetricsSink`], or to none when metrics are disabled.
+///
+/// Any sink converts into a handle, so methods taking `impl Into<Metrics>`
+/// accept either.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    sink: Option<Arc<dyn MetricsSink>>,
+}
+
+impl Metrics {
+    /// Create a handle that reports to `sink`.
+    #[must_use]
+    pub fn new(sink: impl MetricsSink + 'static) -> Self {
+        Self {
+            sink: Some(Arc::new(sink)),
+        }
+    }
+
+    /// Create a handle that reports nothing.
+    #[must_use]
+    pub const fn disabled() -> Self {
+        Self { sink: None }
+    }
+
+    /// Check whether metrics are reported.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    pub(crate) fn increment(&self, counter: Counter, key: &str, value: u64) {
+        if let Some(sink) = &self.sink {
+            sink.increment(counter, key, value);
+        }
+    }
+
+    pub(crate) fn record(&self, histogram: Histogram, key: &str, value: f64) {
+        if let Some(sink) = &self.sink {
+            sink.record(histogram, key, value);
+        }
+    }
+
+    /// Record an API call about `key` started at `started`, which issued
+    /// `issued(value)` IDs if it succeeded.
+    pub(crate) fn record_call<T>(
+        &self,
+        key: &str,
+        started: Instant,
+        outcome: &Result<T>,
+        issued: impl FnOnce(&T) -> usize,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.increment(Counter::Calls, key, 1);
+        self.record(
+            Histogram::CallDuration,
+            key,
+            started.elapsed().as_secs_f64(),
+        );
+        match outcome {
+            Ok(value) => {
+                let count = issued(value);
+                if count > 0 {
+                    self.increment(Counter::IdsIssued, key, count as u64);
+                }
+            }
+            Err(err) => self.increment(Counter::Errors(err.kind()), key, 1),
+        }
+    }
+}
+
+impl<T: MetricsSink + 'static> From<T> for Metrics {
+    fn from(sink: T) -> Self {
+        Self::new(sink)
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+/// Summary of the values recorded in a histogram.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HistogramSummary {
+    /// Number of recorded values.
+    pub count: u64,
+
+    /// Sum of the recorded values.
+    pub sum: f64,
+
+    /// Smallest recorded value.
+    pub min: f64,
+
+    /// Largest recorded value.
+    pub max: f64,
+}
+
+impl HistogramSummary {
+    const fn new(value: f64) -> Self {
+        Self {
+            count: 1,
+            sum: value,
+            min: value,
+            max: value,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Get the mean of the recorded values.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+/// Point-in-time copy of the metrics held by a [`MetricsRecorder`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Counter values by counter and key.
+    pub counters: BTreeMap<(Counter, String), u64>,
+
+    /// Histogram summaries by histogram and key.
+    pub histograms: BTreeMap<(Histogram, String), HistogramSummary>,
+}
+
+impl MetricsSnapshot {
+    /// Get the value of `counter` for `key`, zero if nothing was counted.
+    #[must_use]
+    pub fn counter(&self, counter: Counter, key: &str) -> u64 {
+        self.counters
+            .get(&(counter, key.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Get the number of failed calls for `key`, across all error kinds.
+    #[must_use]
+    pub fn errors(&self, key: &str) -> u64 {
+        self.counters
+            .iter()
+            .filter(|((counter, k), _)| matches!(counter, Counter::Errors(_)) && k == key)
+            .map(|(_, value)| value)
+            .sum()
+    }
+
+    /// Get the summary of `histogram` for `key`, if anything was recorded.
+    #[must_use]
+    pub fn histogram(&self, histogram: Histogram, key: &str) -> Option<HistogramSummary> {
+        self.histograms.get(&(histogram, key.to_string())).copied()
+    }
+}
+
+/// In-memory [`MetricsSink`] that can be read with
+/// [`snapshot`](Self::snapshot).
+///
+/// Clones share the same metrics, so one recorder can be passed to the client
+/// and to generators and read in one place.
+///
+/// # Example
+///
+/// ```
+/// use idbuilder::{Counter, MetricsRecorder, SnowflakeGenerator};
+///
+/// let recorder = MetricsRecorder::new();
+/// let generator = SnowflakeGenerator::new(1, 1704067200000, 10, 12)
+///     .with_metrics("user-id", recorder.clone());
+///
+/// generator.next_ids(3).unwrap();
+/// assert_eq!(recorder.snapshot().counter(Counter::IdsIssued, "user-id"), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRecorder {
+    metrics: Arc<Mutex<MetricsSnapshot>>,
+}
+
+impl MetricsRecorder {
+    /// Create an empty recorder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a copy of the current metrics.
+    #[must_use]
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.lock().clone()
+    }
+
+    /// Clear all metrics.
+    pub fn reset(&self) {
+        *self.lock() = MetricsSnapshot::default();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MetricsSnapshot> {
+        self.metrics
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl MetricsSink for MetricsRecorder {
+    fn increment(&self, counter: Counter, key: &str, value: u64) {
+        *self
+            .lock()
+            .counters
+            .entry((counter, key.to_string()))
+            .or_default() += value;
+    }
+
+    fn record(&self, histogram: Histogram, key: &str, value: f64) {
+        self.lock()
+            .histograms
+            .entry((histogram, key.to_string()))
+            .and_modify(|summary| summary.add(value))
+            .or_insert_with(|| HistogramSummary::new(value));
+    }
+}
+
+/// [`MetricsSink`] that forwards to the `metrics` crate.
+///
+/// Values are labelled with `key`, and errors also with `kind`. Install a
+/// `metrics` recorder, such as a Prometheus exporter, to collect them.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsFacade;
+
+#[cfg(feature = "metrics")]
+impl MetricsSink for MetricsFacade {
+    fn increment(&self, counter: Counter, key: &str, value: u64) {
+        let key = key.to_string();
+        if let Counter::Errors(kind) = counter {
+            let kind = format!("{kind:?}");
+            ::metrics::counter!(counter.name(), "key" => key, "kind" => kind).increment(value);
+            return;
+        }
+        ::metrics::counter!(counter.name(), "key" => key).increment(value);
+    }
+
+    fn record(&self, histogram: Histogram, key: &str, value: f64) {
+        ::metrics::histogram!(histogram.name(), "key" => key.to_string()).record(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_recorder_aggregates_by_key() {
+        let recorder = MetricsRecorder::new();
+        let metrics = Metrics::new(recorder.clone());
+
+        metrics.increment(Counter::IdsIssued, "order-id", 5);
+        metrics.increment(Counter::IdsIssued, "order-id", 3);
+        metrics.increment(Counter::IdsIssued, "user-id", 1);
+        metrics.record(Histogram::RefillSize, "order-id", 100.0);
+        metrics.record(Histogram::RefillSize, "order-id", 300.0);
+
+        let snapshot = recorder.snapshot();
+        assert_eq!(snapshot.counter(Counter::IdsIssued, "order-id"), 8);
+        assert_eq!(snapshot.counter(Counter::IdsIssued, "user-id"), 1);
+        assert_eq!(snapshot.counter(Counter::Calls, "order-id"), 0);
+
+        let refills = snapshot
+            .histogram(Histogram::RefillSize, "order-id")
+            .unwrap();
+        assert_eq!(refills.count, 2);
+        assert!((refills.min - 100.0).abs() < f64::EPSILON);
+        assert!((refills.max - 300.0).abs() < f64::EPSILON);
+        assert!((refills.mean() - 200.0).abs() < f64::EPSILON);
+
+        recorder.reset();
+        assert_eq!(recorder.snapshot(), MetricsSnapshot::default());
+    }
+
+    #[test]
+    fn test_record_call() {
+        let recorder = MetricsRecorder::new();
+        let metrics = Metrics::from(recorder.clone());
+
+        let ok: Result<Vec<i64>> = Ok(vec![1, 2, 3]);
+        metrics.record_call("order-id", Instant::now(), &ok, Vec::len);
+        let failed: Result<Vec<i64>> = Err(Error::SequenceExhausted("order-id".to_string()));
+        metrics.record_call("order-id", Instant::now(), &failed, Vec::len);
+
+        let snapshot = recorder.snapshot();
+        assert_eq!(snapshot.counter(Counter::Calls, "order-id"), 2);
+        assert_eq!(snapshot.counter(Counter::IdsIssued, "order-id"), 3);
+        assert_eq!(
+            snapshot.counter(Counter::Errors(ErrorKind::Exhausted), "order-id"),
+            1
+        );
+        assert_eq!(snapshot.errors("order-id"), 1);
+        assert_eq!(
+            snapshot
+                .histogram(Histogram::CallDuration, "order-id")
+                .map(|summary| summary.count),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_disabled_metrics_report_nothing() {
+        let metrics = Metrics::disabled();
+        assert!(!metrics.is_enabled());
+        metrics.increment(Counter::Calls, "order-id", 1);
+        assert!(format!("{metrics:?}").contains("enabled: false"));
+    }
+}
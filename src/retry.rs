@@ -0,0 +1,603 @@
+//! Retry policy for transient request failures.
+//!
+//! Requests are retried according to a [`RetryPolicy`], which controls the
+//! exponential backoff between attempts, the total time budget, and which
+//! classes of failure ([`RetryClass`]) are retried at all.
+//!
+//! ID allocation endpoints are not idempotent: if the server processed a
+//! request but the response was lost, retrying it allocates a fresh batch and
+//! the first one is skipped. Such requests are therefore only retried on
+//! failures where the server is known not to have processed the request
+//! (see [`RetryClass::is_safe`]), unless
+//! [`RetryPolicy::retry_non_idempotent`] is enabled.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::error::HttpError;
+use crate::http::Response;
+use crate::{Error, ErrorKind, Result};
+
+/// Class of a failed request attempt, used to decide whether to retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryClass {
+    /// The connection could not be established.
+    Connection,
+
+    /// The request timed out or the connection broke after it was sent.
+    Timeout,
+
+    /// The server rejected the request with HTTP 429.
+    RateLimited,
+
+    /// The server responded with HTTP 503 or is down for maintenance.
+    Unavailable,
+
+    /// The server responded with HTTP 500, 502 or 504, or another server
+    /// error code.
+    ServerError,
+}
+
+impl RetryClass {
+    /// Classify an HTTP status code, returning `None` if it is not retryable.
+    #[must_use]
+    pub const fn from_status(status: u16) -> Option<Self> {
+        match status {
+            429 => Some(Self::RateLimited),
+            503 => Some(Self::Unavailable),
+            500 | 502 | 504 => Some(Self::ServerError),
+            _ => None,
+        }
+    }
+
+    /// Classify an error, returning `None` if it is not retryable.
+    ///
+    /// Errors from the server are classified by their [`Error::kind`].
+    #[must_use]
+    pub const fn from_error(err: &Error) -> Option<Self> {
+        match err {
+            Error::Http(HttpError::Connection(_)) => Some(Self::Connection),
+            Error::Http(HttpError::Timeout | HttpError::Interrupted(_)) => Some(Self::Timeout),
+            _ => match err.kind() {
+                ErrorKind::RateLimited => Some(Self::RateLimited),
+                ErrorKind::Unavailable => Some(Self::Unavailable),
+                ErrorKind::Server => Some(Self::ServerError),
+                _ => None,
+            },
+        }
+    }
+
+    /// Whether the server is known not to have processed the request.
+    ///
+    /// Safe failures can be retried even for non-idempotent requests.
+    #[must_use]
+    pub const fn is_safe(self) -> bool {
+        matches!(
+            self,
+            Self::Connection | Self::RateLimited | Self::Unavailable
+        )
+    }
+}
+
+/// Policy controlling how failed requests are retried.
+///
+/// The number of retries is configured separately through
+/// [`ClientConfig::retries`](crate::ClientConfig::retries); this policy decides
+/// how long to wait between attempts and which failures are worth retrying.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use idbuilder::{ClientConfig, RetryPolicy};
+///
+/// let policy = RetryPolicy::new()
+///     .with_initial_backoff(Duration::from_millis(50))
+///     .with_max_elapsed(Some(Duration::from_secs(2)));
+///
+/// let config = ClientConfig::new("http://localhost:8080")
+///     .with_retries(3)
+///     .with_retry_policy(policy);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RetryPolicy {
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+
+    /// Upper bound for the delay between two attempts.
+    pub max_backoff: Duration,
+
+    /// Factor the delay is multiplied by after each attempt.
+    pub multiplier: f64,
+
+    /// Randomize each delay between half and the full computed value.
+    pub jitter: bool,
+
+    /// Total time budget for all attempts; no retry is started past it.
+    pub max_elapsed: Option<Duration>,
+
+    /// Longest `Retry-After` delay that is waited out.
+    ///
+    /// A retryable response asking for a longer delay is returned instead,
+    /// so that the caller sees [`Error::RateLimited`](crate::Error::RateLimited)
+    /// with the requested delay.
+    pub max_retry_after: Duration,
+
+    /// Retry connection failures.
+    pub retry_connection: bool,
+
+    /// Retry timeouts.
+    pub retry_timeout: bool,
+
+    /// Retry HTTP 429 responses.
+    pub retry_rate_limited: bool,
+
+    /// Retry HTTP 503 responses and maintenance errors.
+    pub retry_unavailable: bool,
+
+    /// Retry HTTP 500, 502 and 504 responses and other server errors.
+    pub retry_server_error: bool,
+
+    /// Also retry non-idempotent requests on failures that are not
+    /// [safe](RetryClass::is_safe).
+    ///
+    /// For ID allocation this can skip IDs, but never duplicates them.
+    pub retry_non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Default delay before the first retry (100 milliseconds).
+    pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+    /// Default upper bound for the delay between attempts (5 seconds).
+    pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+    /// Default backoff multiplier.
+    pub const DEFAULT_MULTIPLIER: f64 = 2.0;
+
+    /// Default longest `Retry-After` delay that is waited out (30 seconds).
+    pub const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+    /// Create a policy with default settings.
+    ///
+    /// All failure classes are retryable, jitter is enabled and
+    /// non-idempotent requests are only retried on safe failures.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            initial_backoff: Self::DEFAULT_INITIAL_BACKOFF,
+            max_backoff: Self::DEFAULT_MAX_BACKOFF,
+            multiplier: Self::DEFAULT_MULTIPLIER,
+            jitter: true,
+            max_elapsed: None,
+            max_retry_after: Self::DEFAULT_MAX_RETRY_AFTER,
+            retry_connection: true,
+            retry_timeout: true,
+            retry_rate_limited: true,
+            retry_unavailable: true,
+            retry_server_error: true,
+            retry_non_idempotent: false,
+        }
+    }
+
+    /// Set the delay before the first retry.
+    #[must_use]
+    pub const fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the upper bound for the delay between attempts.
+    #[must_use]
+    pub const fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Set the backoff multiplier.
+    #[must_use]
+    pub const fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Enable or disable jitter.
+    #[must_use]
+    pub const fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the total time budget for all attempts.
+    #[must_use]
+    pub const fn with_max_elapsed(mut self, max_elapsed: Option<Duration>) -> Self {
+        self.max_elapsed = max_elapsed;
+        self
+    }
+
+    /// Set the longest `Retry-After` delay that is waited out.
+    #[must_use]
+    pub const fn with_max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Enable or disable retries for a class of failure.
+    #[must_use]
+    pub const fn with_class(mut self, class: RetryClass, retry: bool) -> Self {
+        match class {
+            RetryClass::Connection => self.retry_connection = retry,
+            RetryClass::Timeout => self.retry_timeout = retry,
+            RetryClass::RateLimited => self.retry_rate_limited = retry,
+            RetryClass::Unavailable => self.retry_unavailable = retry,
+            RetryClass::ServerError => self.retry_server_error = retry,
+        }
+        self
+    }
+
+    /// Allow retrying non-idempotent requests on ambiguous failures.
+    #[must_use]
+    pub const fn with_retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    /// Whether a failure of the given class is retried.
+    #[must_use]
+    pub const fn retries_class(&self, class: RetryClass, idempotent: bool) -> bool {
+        let enabled = match class {
+            RetryClass::Connection => self.retry_connection,
+            RetryClass::Timeout => self.retry_timeout,
+            RetryClass::RateLimited => self.retry_rate_limited,
+            RetryClass::Unavailable => self.retry_unavailable,
+            RetryClass::ServerError => self.retry_server_error,
+        };
+        enabled && (idempotent || class.is_safe() || self.retry_non_idempotent)
+    }
+
+    /// Compute the delay before retry number `attempt` (starting at 0).
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = i32::try_from(attempt).unwrap_or(i32::MAX);
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exp);
+        let capped = base.min(self.max_backoff.as_secs_f64());
+        let delay = Duration::try_from_secs_f64(capped).unwrap_or(self.max_backoff);
+
+        if self.jitter {
+            let half = delay / 2;
+            half + random_fraction_of(delay.saturating_sub(half))
+        } else {
+            delay
+        }
+    }
+
+    /// Decide whether to retry after a failed attempt, returning the delay.
+    ///
+    /// A server-requested `retry_after` replaces a shorter backoff.
+    fn next_delay(
+        &self,
+        class: RetryClass,
+        idempotent: bool,
+        attempt: u32,
+        retries: u32,
+        started: Instant,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if attempt >= retries || !self.retries_class(class, idempotent) {
+            return None;
+        }
+
+        let mut delay = self.backoff(attempt);
+        if let Some(retry_after) = retry_after {
+            if retry_after > self.max_retry_after {
+                return None;
+            }
+            delay = delay.max(retry_after);
+        }
+        if let Some(max_elapsed) = self.max_elapsed {
+            if started.elapsed() + delay > max_elapsed {
+                return None;
+            }
+        }
+        Some(delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Classify the outcome of a single attempt.
+///
+/// A response whose status is not retryable is classified by the error code
+/// in its body, since the server reports failures such as maintenance with
+/// HTTP 200.
+fn classify(outcome: &Result<Response>) -> Option<RetryClass> {
+    match outcome {
+        Ok(response) => {
+            RetryClass::from_status(response.status).or_else(|| classify_body(response))
+        }
+        Err(err) => RetryClass::from_error(err),
+    }
+}
+
+/// Classify a response by the error code in its body.
+fn classify_body(response: &Response) -> Option<RetryClass> {
+    #[derive(Deserialize)]
+    struct Body {
+        code: i32,
+    }
+
+    let body: Body = serde_json::from_str(&response.body).ok()?;
+    if body.code == 0 {
+        return None;
+    }
+    RetryClass::from_error(&Error::from_api("", body.code, String::new()))
+}
+
+/// Get the delay the server asked for in the outcome of an attempt.
+fn retry_after(outcome: &Result<Response>) -> Option<Duration> {
+    match outcome {
+        Ok(response) => response.retry_after(),
+        Err(err) => err.retry_after(),
+    }
+}
+
+/// Run a request, retrying it according to the policy.
+///
+/// Returns the last outcome once it is not retryable or retries are exhausted.
+pub fn execute<F>(
+    policy: &RetryPolicy,
+    retries: u32,
+    idempotent: bool,
+    mut send: F,
+) -> Result<Response>
+where
+    F: FnMut() -> Result<Response>,
+{
+    let started = Instant::now();
+    let mut attempt = 0;
+    loop {
+        let outcome = send();
+        let Some(class) = classify(&outcome) else {
+            return outcome;
+        };
+        let retry_after = retry_after(&outcome);
+        let Some(delay) =
+            policy.next_delay(class, idempotent, attempt, retries, started, retry_after)
+        else {
+            return outcome;
+        };
+        std::thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// Async variant of [`execute`].
+#[cfg(feature = "async")]
+pub async fn execute_async<F, Fut>(
+    policy: &RetryPolicy,
+    retries: u32,
+    idempotent: bool,
+    mut send: F,
+) -> Result<Response>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<Response>>,
+{
+    let started = Instant::now();
+    let mut attempt = 0;
+    loop {
+        let outcome = send().await;
+        let Some(class) = classify(&outcome) else {
+            return outcome;
+        };
+        let retry_after = retry_after(&outcome);
+        let Some(delay) =
+            policy.next_delay(class, idempotent, attempt, retries, started, retry_after)
+        else {
+            return outcome;
+        };
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Return a random duration in `[0, max]`.
+#[allow(clippy::cast_possible_truncation)]
+fn random_fraction_of(max: Duration) -> Duration {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    let nanos = max.as_nanos().min(u128::from(u64::MAX)) as u64;
+    let random = hasher.finish();
+    Duration::from_nanos(nanos.checked_add(1).map_or(random, |bound| random % bound))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn no_delay() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_initial_backoff(Duration::ZERO)
+            .with_jitter(false)
+    }
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(350))
+            .with_jitter(false);
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(350));
+        assert_eq!(policy.backoff(40), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn test_jitter_stays_in_range() {
+        let policy = RetryPolicy::new().with_initial_backoff(Duration::from_millis(100));
+        for _ in 0..100 {
+            let delay = policy.backoff(0);
+            assert!(delay >= Duration::from_millis(50));
+            assert!(delay <= Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn test_non_idempotent_only_retries_safe_classes() {
+        let policy = RetryPolicy::new();
+        assert!(policy.retries_class(RetryClass::Connection, false));
+        assert!(policy.retries_class(RetryClass::RateLimited, false));
+        assert!(!policy.retries_class(RetryClass::Timeout, false));
+        assert!(!policy.retries_class(RetryClass::ServerError, false));
+        assert!(policy.retries_class(RetryClass::Timeout, true));
+
+        let policy = policy.with_retry_non_idempotent(true);
+        assert!(policy.retries_class(RetryClass::Timeout, false));
+
+        let policy = policy.with_class(RetryClass::Timeout, false);
+        assert!(!policy.retries_class(RetryClass::Timeout, true));
+    }
+
+    #[test]
+    fn test_execute_retries_until_success() {
+        let calls = Cell::new(0);
+        let response = execute(&no_delay(), 3, false, || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                Ok(Response::new(503, String::new()))
+            } else {
+                Ok(Response::new(200, String::new()))
+            }
+        })
+        .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test_execute_retries_error_code_in_body() {
+        let maintenance = r#"{"code":50301,"message":"maintenance","data":null}"#;
+        let success = r#"{"code":0,"message":"success","data":{"ids":[1]}}"#;
+        let calls = Cell::new(0);
+        let response = execute(&no_delay(), 2, false, || {
+            calls.set(calls.get() + 1);
+            let body = if calls.get() < 2 {
+                maintenance
+            } else {
+                success
+            };
+            Ok(Response::new(200, body.to_string()))
+        })
+        .unwrap();
+
+        assert_eq!(response.body, success);
+        assert_eq!(calls.get(), 2);
+
+        let calls = Cell::new(0);
+        let response = execute(&no_delay(), 2, false, || {
+            calls.set(calls.get() + 1);
+            Ok(Response::new(200, maintenance.to_string()))
+        })
+        .unwrap();
+
+        assert_eq!(response.body, maintenance);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test_execute_stops_after_retries() {
+        let calls = Cell::new(0);
+        let result = execute(&no_delay(), 2, false, || {
+            calls.set(calls.get() + 1);
+            Err(HttpError::Connection("refused".to_string()).into())
+        });
+
+        assert!(matches!(result, Err(Error::Http(HttpError::Connection(_)))));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test_execute_does_not_retry_ambiguous_allocation() {
+        let calls = Cell::new(0);
+        let result = execute(&no_delay(), 5, false, || {
+            calls.set(calls.get() + 1);
+            Err(HttpError::Timeout.into())
+        });
+
+        assert!(matches!(result, Err(Error::Http(HttpError::Timeout))));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_execute_does_not_retry_interrupted_allocation() {
+        let calls = Cell::new(0);
+        let result = execute(&no_delay(), 5, false, || {
+            calls.set(calls.get() + 1);
+            Err(HttpError::Interrupted("connection reset".to_string()).into())
+        });
+
+        assert!(matches!(
+            result,
+            Err(Error::Http(HttpError::Interrupted(_)))
+        ));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_execute_respects_max_elapsed() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_secs(10))
+            .with_max_elapsed(Some(Duration::from_millis(10)));
+        let calls = Cell::new(0);
+        let response = execute(&policy, 5, true, || {
+            calls.set(calls.get() + 1);
+            Ok(Response::new(429, String::new()))
+        })
+        .unwrap();
+
+        assert_eq!(response.status, 429);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_retry_after_replaces_shorter_backoff() {
+        let policy = no_delay().with_max_retry_after(Duration::from_secs(5));
+        let started = Instant::now();
+        let retry_after = Some(Duration::from_secs(2));
+
+        assert_eq!(
+            policy.next_delay(RetryClass::RateLimited, false, 0, 3, started, retry_after),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            policy.next_delay(RetryClass::RateLimited, false, 0, 3, started, None),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_execute_returns_long_retry_after() {
+        let policy = no_delay().with_max_retry_after(Duration::from_secs(1));
+        let calls = Cell::new(0);
+        let response = execute(&policy, 5, true, || {
+            calls.set(calls.get() + 1);
+            Ok(Response::new(429, String::new()).with_header("Retry-After", "60"))
+        })
+        .unwrap();
+
+        assert_eq!(response.retry_after(), Some(Duration::from_secs(60)));
+        assert_eq!(calls.get(), 1);
+    }
+}
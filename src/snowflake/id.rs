@@ -0,0 +1,375 @@
+//! Typed snowflake IDs and their bit layout.
+
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Error;
+
+/// Bit layout of a snowflake ID.
+///
+/// From the most significant bit down, an ID holds the milliseconds since
+/// `epoch`, the worker ID and the per-millisecond sequence number. The sign
+/// bit is never set, so `worker_bits + sequence_bits` is at most 63; the
+/// constructors and deserialization reject anything larger, and the fields
+/// can only be read.
+///
+/// # Example
+///
+/// ```
+/// use idbuilder::{SnowflakeId, SnowflakeLayout};
+///
+/// let layout = SnowflakeLayout::new(1_704_067_200_000, 10, 12);
+/// let id = layout.compose(1_704_067_200_123, 42, 7);
+///
+/// let parts = layout.parts(id);
+/// assert_eq!(parts.timestamp_millis, 1_704_067_200_123);
+/// assert_eq!(parts.worker_id, 42);
+/// assert_eq!(parts.sequence, 7);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "LayoutFields")]
+pub struct SnowflakeLayout {
+    epoch: i64,
+    worker_bits: u8,
+    sequence_bits: u8,
+}
+
+impl SnowflakeLayout {
+    /// Largest number of bits for the worker ID and sequence number together.
+    pub const MAX_BITS: u8 = 63;
+
+    /// Create a new layout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker_bits + sequence_bits` exceeds [`Self::MAX_BITS`]. Use
+    /// [`try_new`](Self::try_new) for bit counts that are not known to fit.
+    #[must_use]
+    pub const fn new(epoch: i64, worker_bits: u8, sequence_bits: u8) -> Self {
+        assert!(
+            worker_bits as u16 + sequence_bits as u16 <= Self::MAX_BITS as u16,
+            "worker and sequence bits exceed 63"
+        );
+        Self {
+            epoch,
+            worker_bits,
+            sequence_bits,
+        }
+    }
+
+    /// Create a new layout, checking that the bits fit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if `worker_bits + sequence_bits`
+    /// exceeds [`Self::MAX_BITS`].
+    pub fn try_new(epoch: i64, worker_bits: u8, sequence_bits: u8) -> crate::Result<Self> {
+        if u16::from(worker_bits) + u16::from(sequence_bits) > u16::from(Self::MAX_BITS) {
+            return Err(Error::InvalidConfig(format!(
+                "worker bits ({worker_bits}) and sequence bits ({sequence_bits}) exceed {}",
+                Self::MAX_BITS
+            )));
+        }
+        Ok(Self::new(epoch, worker_bits, sequence_bits))
+    }
+
+    /// Custom epoch timestamp in milliseconds.
+    #[must_use]
+    pub const fn epoch(&self) -> i64 {
+        self.epoch
+    }
+
+    /// Number of bits for the worker ID.
+    #[must_use]
+    pub const fn worker_bits(&self) -> u8 {
+        self.worker_bits
+    }
+
+    /// Number of bits for the sequence number.
+    #[must_use]
+    pub const fn sequence_bits(&self) -> u8 {
+        self.sequence_bits
+    }
+
+    /// Number of bits available for the timestamp.
+    #[must_use]
+    pub const fn timestamp_bits(&self) -> u8 {
+        Self::MAX_BITS - self.worker_bits - self.sequence_bits
+    }
+
+    /// Check that a worker ID fits the layout.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if `worker_id` exceeds
+    /// [`max_worker_id`](Self::max_worker_id).
+    pub fn check_worker_id(&self, worker_id: u32) -> crate::Result<()> {
+        if i64::from(worker_id) > self.max_worker_id() {
+            return Err(Error::InvalidConfig(format!(
+                "worker ID {worker_id} does not fit in {} bits",
+                self.worker_bits
+            )));
+        }
+        Ok(())
+    }
+
+    /// Largest worker ID that fits the layout.
+    #[must_use]
+    pub const fn max_worker_id(&self) -> i64 {
+        (1_i64 << self.worker_bits) - 1
+    }
+
+    /// Largest sequence number that fits the layout.
+    #[must_use]
+    pub const fn max_sequence(&self) -> i64 {
+        (1_i64 << self.sequence_bits) - 1
+    }
+
+    /// Compose an ID from a Unix timestamp in milliseconds, a worker ID and a
+    /// sequence number.
+    #[must_use]
+    pub const fn compose(
+        &self,
+        timestamp_millis: i64,
+        worker_id: u32,
+        sequence: i64,
+    ) -> SnowflakeId {
+        let worker_shift = self.sequence_bits as u32;
+        let ts_shift = self.worker_bits as u32 + self.sequence_bits as u32;
+
+        SnowflakeId(
+            ((timestamp_millis - self.epoch) << ts_shift)
+                | ((worker_id as i64) << worker_shift)
+                | sequence,
+        )
+    }
+
+    /// Split an ID into its named parts.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn parts(&self, id: SnowflakeId) -> SnowflakeParts {
+        let id = id.0;
+        let worker_shift = self.sequence_bits;
+        let ts_shift = self.worker_bits + self.sequence_bits;
+
+        let timestamp_millis = (id >> ts_shift) + self.epoch;
+        let worker_id = ((id >> worker_shift) & self.max_worker_id()) as u32;
+        let sequence = (id & self.max_sequence()) as u64;
+
+        SnowflakeParts {
+            timestamp: UNIX_EPOCH + Duration::from_millis(timestamp_millis.max(0) as u64),
+            timestamp_millis,
+            worker_id,
+            sequence,
+        }
+    }
+}
+
+/// Fields of a serialized [`SnowflakeLayout`], checked before use.
+#[derive(Deserialize)]
+struct LayoutFields {
+    epoch: i64,
+    worker_bits: u8,
+    sequence_bits: u8,
+}
+
+impl TryFrom<LayoutFields> for SnowflakeLayout {
+    type Error = Error;
+
+    fn try_from(fields: LayoutFields) -> crate::Result<Self> {
+        Self::try_new(fields.epoch, fields.worker_bits, fields.sequence_bits)
+    }
+}
+
+/// Named parts of a snowflake ID, see [`SnowflakeLayout::parts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SnowflakeParts {
+    /// Time the ID was generated.
+    pub timestamp: SystemTime,
+
+    /// Time the ID was generated, in milliseconds since the Unix epoch.
+    pub timestamp_millis: i64,
+
+    /// Worker ID that generated the ID.
+    pub worker_id: u32,
+
+    /// Sequence number within the millisecond.
+    pub sequence: u64,
+}
+
+/// A snowflake ID.
+///
+/// A thin wrapper around the raw `i64` that orders, formats and parses like
+/// the number itself. It serializes as a JSON number and deserializes from
+/// either a number or a decimal string, since JavaScript clients often pass
+/// 64-bit IDs as strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnowflakeId(i64);
+
+impl SnowflakeId {
+    /// Wrap a raw ID.
+    #[must_use]
+    pub const fn new(id: i64) -> Self {
+        Self(id)
+    }
+
+    /// Get the raw ID.
+    #[must_use]
+    pub const fn get(self) -> i64 {
+        self.0
+    }
+
+    /// Split the ID into its named parts using the given layout.
+    #[must_use]
+    pub fn parts(self, layout: &SnowflakeLayout) -> SnowflakeParts {
+        layout.parts(self)
+    }
+}
+
+impl From<i64> for SnowflakeId {
+    fn from(id: i64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<SnowflakeId> for i64 {
+    fn from(id: SnowflakeId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for SnowflakeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for SnowflakeId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+impl Serialize for SnowflakeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SnowflakeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdVisitor;
+
+        impl Visitor<'_> for IdVisitor {
+            type Value = SnowflakeId;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a snowflake ID as an integer or decimal string")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(SnowflakeId(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                i64::try_from(v)
+                    .map(SnowflakeId)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_any(IdVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: SnowflakeLayout = SnowflakeLayout::new(1_704_067_200_000, 10, 12);
+
+    #[test]
+    fn test_compose_and_parts_round_trip() {
+        let id = LAYOUT.compose(1_704_067_260_123, 1023, 4095);
+        let parts = id.parts(&LAYOUT);
+
+        assert_eq!(parts.timestamp_millis, 1_704_067_260_123);
+        assert_eq!(
+            parts.timestamp,
+            UNIX_EPOCH + Duration::from_millis(1_704_067_260_123)
+        );
+        assert_eq!(parts.worker_id, 1023);
+        assert_eq!(parts.sequence, 4095);
+    }
+
+    #[test]
+    fn test_layout_limits() {
+        assert_eq!(LAYOUT.timestamp_bits(), 41);
+        assert_eq!(LAYOUT.max_worker_id(), 1023);
+        assert_eq!(LAYOUT.max_sequence(), 4095);
+    }
+
+    #[test]
+    fn test_layout_rejects_too_many_bits() {
+        assert!(SnowflakeLayout::try_new(0, 22, 41).is_ok());
+        assert!(matches!(
+            SnowflakeLayout::try_new(0, 40, 30),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(SnowflakeLayout::try_new(0, 255, 255).is_err());
+
+        let json = r#"{"epoch":0,"worker_bits":64,"sequence_bits":12}"#;
+        assert!(serde_json::from_str::<SnowflakeLayout>(json).is_err());
+    }
+
+    #[test]
+    fn test_check_worker_id() {
+        assert!(LAYOUT.check_worker_id(1023).is_ok());
+        assert!(matches!(
+            LAYOUT.check_worker_id(1024),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_ordering_follows_time() {
+        let earlier = LAYOUT.compose(1_704_067_200_001, 1023, 4095);
+        let later = LAYOUT.compose(1_704_067_200_002, 0, 0);
+        assert!(earlier < later);
+    }
+
+    #[test]
+    fn test_display_and_from_str() {
+        let id = SnowflakeId::new(1_234_567_890_123);
+        assert_eq!(id.to_string(), "1234567890123");
+        assert_eq!("1234567890123".parse::<SnowflakeId>().unwrap(), id);
+        assert!("not-an-id".parse::<SnowflakeId>().is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let id = SnowflakeId::new(1_234_567_890_123);
+        assert_eq!(serde_json::to_string(&id).unwrap(), "1234567890123");
+        assert_eq!(
+            serde_json::from_str::<SnowflakeId>("1234567890123").unwrap(),
+            id
+        );
+        assert_eq!(
+            serde_json::from_str::<SnowflakeId>(r#""1234567890123""#).unwrap(),
+            id
+        );
+        assert!(serde_json::from_str::<SnowflakeId>("18446744073709551615").is_err());
+    }
+}
@@ -0,0 +1,699 @@
+//! Local snowflake ID generator.
+//!
+//! This module provides a thread-safe snowflake ID generator that can be used
+//! after fetching the configuration from the server.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::metrics::{Counter, Histogram, Metrics};
+use crate::trace;
+use crate::{Error, Result};
+
+mod id;
+
+pub use id::{SnowflakeId, SnowflakeLayout, SnowflakeParts};
+
+/// How a [`SnowflakeGenerator`] reacts when the system clock moves backwards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClockRollbackPolicy {
+    /// Return [`Error::ClockMovedBackwards`] immediately.
+    #[default]
+    Fail,
+
+    /// Wait for the clock to catch up, failing if the rollback exceeds the
+    /// given duration.
+    Wait(Duration),
+
+    /// Keep issuing IDs from the last seen timestamp, moving it forward when
+    /// its sequence is exhausted.
+    ///
+    /// IDs stay unique and increasing, but their embedded timestamps run ahead
+    /// of the wall clock until it catches up.
+    Logical,
+}
+
+/// Counters of how often each clock rollback path was taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RollbackStats {
+    /// Rollbacks that resulted in [`Error::ClockMovedBackwards`].
+    pub failed: u64,
+
+    /// Rollbacks that were resolved by waiting for the clock.
+    pub waited: u64,
+
+    /// IDs issued from the logical clock while the system clock was behind.
+    pub borrowed: u64,
+}
+
+/// Thread-safe local snowflake ID generator.
+///
+/// Generates unique 64-bit IDs composed of:
+/// - Timestamp (milliseconds since epoch)
+/// - Worker ID (assigned by the server)
+/// - Sequence number (per-millisecond counter)
+///
+/// Time is read from a [`Clock`], the system clock by default. Use
+/// [`with_clock`](Self::with_clock) to choose another time source.
+///
+/// # Example
+///
+/// ```
+/// use idbuilder::SnowflakeGenerator;
+///
+/// let generator = SnowflakeGenerator::new(1, 1704067200000, 10, 12);
+/// let id = generator.next_id().unwrap();
+/// println!("Generated ID: {}", id);
+/// ```
+#[derive(Debug)]
+pub struct SnowflakeGenerator<K = SystemClock> {
+    /// Bit layout of generated IDs.
+    layout: SnowflakeLayout,
+
+    /// Allocated worker ID.
+    worker_id: u32,
+
+    /// Maximum sequence value before overflow.
+    max_sequence: i64,
+
+    /// Timestamp (relative to the epoch) and sequence of the last issued ID,
+    /// packed into one word so both are updated by a single CAS.
+    state: AtomicU64,
+
+    /// Behavior when the clock moves backwards.
+    rollback_policy: ClockRollbackPolicy,
+
+    /// Number of rollbacks that failed.
+    rollbacks_failed: AtomicU64,
+
+    /// Number of rollbacks resolved by waiting.
+    rollbacks_waited: AtomicU64,
+
+    /// Number of IDs issued from the logical clock.
+    ids_borrowed: AtomicU64,
+
+    /// Where issued IDs, overflow waits and rollbacks are reported.
+    metrics: Metrics,
+
+    /// Key the metrics are labelled with.
+    metrics_key: String,
+
+    /// Time source.
+    clock: K,
+}
+
+impl SnowflakeGenerator {
+    /// Create a new snowflake generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `worker_id` - The worker ID assigned by the server
+    /// * `epoch` - Custom epoch timestamp in milliseconds
+    /// * `worker_bits` - Number of bits allocated for worker ID
+    /// * `sequence_bits` - Number of bits allocated for sequence number
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker_bits + sequence_bits` exceeds
+    /// [`SnowflakeLayout::MAX_BITS`].
+    #[must_use]
+    pub const fn new(worker_id: u32, epoch: i64, worker_bits: u8, sequence_bits: u8) -> Self {
+        Self::with_layout(
+            worker_id,
+            SnowflakeLayout::new(epoch, worker_bits, sequence_bits),
+        )
+    }
+
+    /// Create a new snowflake generator for the given layout.
+    ///
+    /// The worker ID must fit the layout, or it spills into the timestamp
+    /// bits of generated IDs; use [`try_with_layout`](Self::try_with_layout)
+    /// to check it.
+    #[must_use]
+    pub const fn with_layout(worker_id: u32, layout: SnowflakeLayout) -> Self {
+        Self {
+            layout,
+            worker_id,
+            max_sequence: layout.max_sequence(),
+            state: AtomicU64::new(0),
+            rollback_policy: ClockRollbackPolicy::Fail,
+            rollbacks_failed: AtomicU64::new(0),
+            rollbacks_waited: AtomicU64::new(0),
+            ids_borrowed: AtomicU64::new(0),
+            metrics: Metrics::disabled(),
+            metrics_key: String::new(),
+            clock: SystemClock,
+        }
+    }
+
+    /// Create a new snowflake generator for the given layout, checking that
+    /// the worker ID fits it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if `worker_id` exceeds
+    /// [`SnowflakeLayout::max_worker_id`].
+    pub fn try_with_layout(worker_id: u32, layout: SnowflakeLayout) -> Result<Self> {
+        layout.check_worker_id(worker_id)?;
+        Ok(Self::with_layout(worker_id, layout))
+    }
+}
+
+impl<K: Clock> SnowflakeGenerator<K> {
+    /// Replace the time source of this generator.
+    #[must_use]
+    pub fn with_clock<T: Clock>(self, clock: T) -> SnowflakeGenerator<T> {
+        SnowflakeGenerator {
+            layout: self.layout,
+            worker_id: self.worker_id,
+            max_sequence: self.max_sequence,
+            state: self.state,
+            rollback_policy: self.rollback_policy,
+            rollbacks_failed: self.rollbacks_failed,
+            rollbacks_waited: self.rollbacks_waited,
+            ids_borrowed: self.ids_borrowed,
+            metrics: self.metrics,
+            metrics_key: self.metrics_key,
+            clock,
+        }
+    }
+
+    /// Set the behavior when the system clock moves backwards.
+    #[must_use]
+    pub const fn with_rollback_policy(mut self, policy: ClockRollbackPolicy) -> Self {
+        self.rollback_policy = policy;
+        self
+    }
+
+    /// Report issued IDs, overflow waits and clock rollbacks, labelled with
+    /// `key`.
+    #[must_use]
+    pub fn with_metrics(mut self, key: impl Into<String>, metrics: impl Into<Metrics>) -> Self {
+        self.metrics_key = key.into();
+        self.metrics = metrics.into();
+        self
+    }
+
+    /// Generate the next unique ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The system clock moved backwards and the [`ClockRollbackPolicy`]
+    ///   does not tolerate it
+    /// - The sequence overflows within a single millisecond (will wait for next ms)
+    ///
+    /// # Thread Safety
+    ///
+    /// This method is safe to call from multiple threads concurrently.
+    pub fn next_id(&self) -> Result<i64> {
+        let id = self.issue()?;
+        self.metrics
+            .increment(Counter::IdsIssued, &self.metrics_key, 1);
+        Ok(id)
+    }
+
+    fn issue(&self) -> Result<i64> {
+        loop {
+            // Load the state before reading the clock, so a timestamp issued
+            // by another thread in between cannot look like a rollback.
+            let current = self.state.load(Ordering::Acquire);
+            let (last_ts, last_seq) = self.unpack(current);
+
+            // The unpacked timestamp is never before the epoch, so a clock
+            // reading before the epoch is handled as a rollback below.
+            let now = self.clock.now_millis()?;
+            // How far the wall clock is behind while borrowing from the
+            // logical clock; reported once the ID is actually issued.
+            let mut borrowing = None;
+
+            let (timestamp, sequence) = if now > last_ts {
+                // New millisecond, reset sequence
+                (now, 0)
+            } else {
+                if now < last_ts {
+                    match self.rollback_policy {
+                        ClockRollbackPolicy::Fail => {
+                            self.rollbacks_failed.fetch_add(1, Ordering::Relaxed);
+                            self.clock_rollback(last_ts - now, "fail");
+                            return Err(Error::ClockMovedBackwards);
+                        }
+                        ClockRollbackPolicy::Wait(max_wait) => {
+                            self.wait_for_clock(last_ts, max_wait)?;
+                            continue;
+                        }
+                        ClockRollbackPolicy::Logical => borrowing = Some(last_ts - now),
+                    }
+                }
+
+                if last_seq < self.max_sequence {
+                    // Same millisecond, increment sequence
+                    (last_ts, last_seq + 1)
+                } else if borrowing.is_some() {
+                    // The wall clock is behind, so waiting would not help:
+                    // move the logical clock to the next millisecond instead.
+                    (last_ts + 1, 0)
+                } else {
+                    // Sequence overflow, wait for next millisecond
+                    self.wait_for_next_millis(last_ts)?;
+                    continue;
+                }
+            };
+
+            if self
+                .state
+                .compare_exchange_weak(
+                    current,
+                    self.pack(timestamp, sequence),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+            {
+                if let Some(behind_ms) = borrowing {
+                    self.ids_borrowed.fetch_add(1, Ordering::Relaxed);
+                    self.clock_rollback(behind_ms, "borrow");
+                }
+                return Ok(self.compose_id(timestamp, sequence));
+            }
+
+            // Another thread issued an ID in the meantime, retry
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    const fn pack(&self, timestamp: i64, sequence: i64) -> u64 {
+        (((timestamp - self.layout.epoch()) as u64) << self.layout.sequence_bits())
+            | sequence as u64
+    }
+
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    const fn unpack(&self, state: u64) -> (i64, i64) {
+        let timestamp = (state >> self.layout.sequence_bits()) as i64 + self.layout.epoch();
+        let sequence = (state & self.max_sequence as u64) as i64;
+        (timestamp, sequence)
+    }
+
+    /// Generate the next unique ID as a [`SnowflakeId`].
+    ///
+    /// # Errors
+    ///
+    /// See [`next_id`](Self::next_id).
+    pub fn next_snowflake_id(&self) -> Result<SnowflakeId> {
+        self.next_id().map(SnowflakeId::new)
+    }
+
+    /// Generate multiple IDs at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of IDs to generate
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any ID generation fails.
+    pub fn next_ids(&self, count: usize) -> Result<Vec<i64>> {
+        let mut ids = Vec::with_capacity(count);
+        let outcome = (0..count).try_for_each(|_| self.issue().map(|id| ids.push(id)));
+        self.metrics
+            .increment(Counter::IdsIssued, &self.metrics_key, ids.len() as u64);
+        outcome.map(|()| ids)
+    }
+
+    /// Get the worker ID.
+    #[must_use]
+    pub const fn worker_id(&self) -> u32 {
+        self.worker_id
+    }
+
+    /// Get the epoch.
+    #[must_use]
+    pub const fn epoch(&self) -> i64 {
+        self.layout.epoch()
+    }
+
+    /// Get the bit layout of generated IDs.
+    #[must_use]
+    pub const fn layout(&self) -> &SnowflakeLayout {
+        &self.layout
+    }
+
+    /// Get the clock rollback policy.
+    #[must_use]
+    pub const fn rollback_policy(&self) -> ClockRollbackPolicy {
+        self.rollback_policy
+    }
+
+    /// Get counters of how often each clock rollback path was taken.
+    #[must_use]
+    pub fn rollback_stats(&self) -> RollbackStats {
+        RollbackStats {
+            failed: self.rollbacks_failed.load(Ordering::Relaxed),
+            waited: self.rollbacks_waited.load(Ordering::Relaxed),
+            borrowed: self.ids_borrowed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Decompose an ID into its components.
+    ///
+    /// Returns a tuple of (timestamp, worker ID, sequence). See
+    /// [`SnowflakeLayout::parts`] for a version with named fields.
+    #[must_use]
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn decompose(&self, id: i64) -> (i64, u32, i64) {
+        let parts = self.layout.parts(SnowflakeId::new(id));
+        (
+            parts.timestamp_millis,
+            parts.worker_id,
+            parts.sequence as i64,
+        )
+    }
+
+    const fn compose_id(&self, timestamp: i64, sequence: i64) -> i64 {
+        self.layout
+            .compose(timestamp, self.worker_id, sequence)
+            .get()
+    }
+
+    /// Wait until the clock reaches `last_ts`, unless it is too far behind.
+    #[allow(clippy::cast_sign_loss)]
+    fn wait_for_clock(&self, last_ts: i64, max_wait: Duration) -> Result<()> {
+        let behind_ms = (last_ts - self.clock.now_millis()?).max(0);
+        let behind = Duration::from_millis(behind_ms as u64);
+        if behind > max_wait {
+            self.rollbacks_failed.fetch_add(1, Ordering::Relaxed);
+            self.clock_rollback(behind_ms, "fail");
+            return Err(Error::ClockMovedBackwards);
+        }
+
+        self.rollbacks_waited.fetch_add(1, Ordering::Relaxed);
+        self.clock_rollback(behind_ms, "wait");
+        self.clock.wait_until(last_ts)?;
+        Ok(())
+    }
+
+    /// Wait for the millisecond after `last_ts`, whose sequence is used up.
+    fn wait_for_next_millis(&self, last_ts: i64) -> Result<()> {
+        trace::sequence_wait(self.worker_id, last_ts);
+        let started = Instant::now();
+        self.clock.wait_until(last_ts + 1)?;
+        self.metrics
+            .increment(Counter::OverflowWaits, &self.metrics_key, 1);
+        self.metrics.record(
+            Histogram::OverflowWait,
+            &self.metrics_key,
+            started.elapsed().as_secs_f64(),
+        );
+        Ok(())
+    }
+
+    /// Report that the clock is `behind_ms` behind and how it is handled.
+    fn clock_rollback(&self, behind_ms: i64, action: &'static str) {
+        trace::clock_rollback(self.worker_id, behind_ms, action);
+        self.metrics
+            .increment(Counter::ClockRollbacks, &self.metrics_key, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_generate_single_id() {
+        let gen = SnowflakeGenerator::new(1, 1_704_067_200_000, 10, 12);
+        let id = gen.next_id().unwrap();
+        assert!(id > 0);
+    }
+
+    #[test]
+    fn test_generate_multiple_ids() {
+        let gen = SnowflakeGenerator::new(1, 1_704_067_200_000, 10, 12);
+        let ids = gen.next_ids(100).unwrap();
+
+        assert_eq!(ids.len(), 100);
+
+        // All IDs should be unique
+        let mut sorted = ids.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 100);
+
+        // IDs should be monotonically increasing
+        for window in ids.windows(2) {
+            assert!(window[1] > window[0]);
+        }
+    }
+
+    #[test]
+    fn test_decompose_id() {
+        let gen = SnowflakeGenerator::new(42, 1_704_067_200_000, 10, 12);
+        let id = gen.next_id().unwrap();
+
+        let (timestamp, worker_id, sequence) = gen.decompose(id);
+
+        assert_eq!(worker_id, 42);
+        assert!(timestamp > 1_704_067_200_000);
+        assert!(sequence < (1 << 12));
+    }
+
+    #[test]
+    fn test_thread_safety() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let gen = Arc::new(SnowflakeGenerator::new(1, 1_704_067_200_000, 10, 12));
+        let mut handles = vec![];
+
+        for _ in 0..4 {
+            let gen = Arc::clone(&gen);
+            handles.push(thread::spawn(move || gen.next_ids(1000).unwrap()));
+        }
+
+        let mut all_ids = vec![];
+        for handle in handles {
+            all_ids.extend(handle.join().unwrap());
+        }
+
+        // All IDs should be unique
+        let count = all_ids.len();
+        all_ids.sort_unstable();
+        all_ids.dedup();
+        assert_eq!(all_ids.len(), count);
+    }
+
+    #[test]
+    fn test_worker_id_accessor() {
+        let gen = SnowflakeGenerator::new(123, 1_704_067_200_000, 10, 12);
+        assert_eq!(gen.worker_id(), 123);
+    }
+
+    #[test]
+    fn test_epoch_accessor() {
+        let gen = SnowflakeGenerator::new(1, 1_704_067_200_000, 10, 12);
+        assert_eq!(gen.epoch(), 1_704_067_200_000);
+    }
+
+    const EPOCH: i64 = 1_704_067_200_000;
+
+    fn mock_generator(
+        sequence_bits: u8,
+        policy: ClockRollbackPolicy,
+    ) -> (SnowflakeGenerator<MockClock>, MockClock) {
+        let clock = MockClock::new(EPOCH + 1000);
+        let gen = SnowflakeGenerator::new(1, EPOCH, 10, sequence_bits)
+            .with_rollback_policy(policy)
+            .with_clock(clock.clone());
+        (gen, clock)
+    }
+
+    #[test]
+    fn test_worker_id_must_fit_layout() {
+        use crate::SnowflakeIdResponse;
+
+        let layout = SnowflakeLayout::new(EPOCH, 10, 12);
+        assert!(SnowflakeGenerator::try_with_layout(1023, layout).is_ok());
+        assert!(matches!(
+            SnowflakeGenerator::try_with_layout(1024, layout),
+            Err(Error::InvalidConfig(_))
+        ));
+
+        let response = SnowflakeIdResponse {
+            worker_id: 5,
+            epoch: EPOCH,
+            worker_bits: 10,
+            sequence_bits: 12,
+        };
+        assert_eq!(
+            response.clone().try_into_generator().unwrap().worker_id(),
+            5
+        );
+        let too_many_bits = SnowflakeIdResponse {
+            worker_bits: 60,
+            ..response
+        };
+        assert!(too_many_bits.try_into_generator().is_err());
+        let worker_too_large = SnowflakeIdResponse {
+            worker_id: 4096,
+            ..response
+        };
+        assert!(worker_too_large.try_into_generator().is_err());
+    }
+
+    #[test]
+    fn test_sequence_overflow_waits_for_next_millis() {
+        let (gen, clock) = mock_generator(2, ClockRollbackPolicy::Fail);
+
+        let ids = gen.next_ids(5).unwrap();
+        let (timestamp, _, sequence) = gen.decompose(ids[4]);
+        assert_eq!(timestamp, EPOCH + 1001);
+        assert_eq!(sequence, 0);
+        assert_eq!(clock.get(), EPOCH + 1001);
+    }
+
+    #[test]
+    fn test_rollback_fail() {
+        let (gen, clock) = mock_generator(12, ClockRollbackPolicy::Fail);
+        gen.next_id().unwrap();
+        clock.rewind(Duration::from_secs(1));
+
+        assert!(matches!(gen.next_id(), Err(Error::ClockMovedBackwards)));
+        assert_eq!(gen.rollback_stats().failed, 1);
+    }
+
+    #[test]
+    fn test_rollback_wait() {
+        let (gen, clock) =
+            mock_generator(12, ClockRollbackPolicy::Wait(Duration::from_millis(100)));
+        let first = gen.next_id().unwrap();
+        clock.rewind(Duration::from_millis(5));
+
+        let second = gen.next_id().unwrap();
+        assert!(second > first);
+        assert_eq!(clock.get(), EPOCH + 1000);
+        assert_eq!(gen.rollback_stats().waited, 1);
+    }
+
+    #[test]
+    fn test_rollback_wait_too_long() {
+        let (gen, clock) = mock_generator(12, ClockRollbackPolicy::Wait(Duration::from_millis(1)));
+        gen.next_id().unwrap();
+        clock.rewind(Duration::from_secs(10));
+
+        assert!(matches!(gen.next_id(), Err(Error::ClockMovedBackwards)));
+        assert_eq!(gen.rollback_stats().failed, 1);
+    }
+
+    #[test]
+    fn test_rollback_logical() {
+        let (gen, clock) = mock_generator(2, ClockRollbackPolicy::Logical);
+        gen.next_id().unwrap();
+        clock.rewind(Duration::from_secs(10));
+
+        let ids = gen.next_ids(10).unwrap();
+        for window in ids.windows(2) {
+            assert!(window[1] > window[0]);
+        }
+
+        // 4 IDs per millisecond with 2 sequence bits: the logical clock has
+        // moved forward past the sequence borrowed from EPOCH + 1000.
+        let (timestamp, _, _) = gen.decompose(ids[9]);
+        assert_eq!(timestamp, EPOCH + 1002);
+        assert_eq!(gen.rollback_stats().borrowed, 10);
+
+        // Once the wall clock catches up, IDs follow it again.
+        clock.set(EPOCH + 2000);
+        let (timestamp, _, _) = gen.decompose(gen.next_id().unwrap());
+        assert_eq!(timestamp, EPOCH + 2000);
+        assert_eq!(gen.rollback_stats().borrowed, 10);
+    }
+
+    #[test]
+    fn test_metrics() {
+        use crate::{Counter, Histogram, MetricsRecorder};
+
+        let recorder = MetricsRecorder::new();
+        let (gen, clock) = mock_generator(2, ClockRollbackPolicy::Fail);
+        let gen = gen.with_metrics("user-id", recorder.clone());
+
+        gen.next_ids(5).unwrap();
+        clock.rewind(Duration::from_secs(1));
+        gen.next_id().unwrap_err();
+
+        let snapshot = recorder.snapshot();
+        assert_eq!(snapshot.counter(Counter::IdsIssued, "user-id"), 5);
+        assert_eq!(snapshot.counter(Counter::OverflowWaits, "user-id"), 1);
+        assert_eq!(snapshot.counter(Counter::ClockRollbacks, "user-id"), 1);
+        assert_eq!(
+            snapshot
+                .histogram(Histogram::OverflowWait, "user-id")
+                .map(|summary| summary.count),
+            Some(1)
+        );
+    }
+
+    fn assert_unique_across_threads(gen: &Arc<SnowflakeGenerator<MockClock>>, threads: usize) {
+        use std::thread;
+
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                let gen = Arc::clone(gen);
+                thread::spawn(move || gen.next_ids(2000).unwrap())
+            })
+            .collect();
+
+        let mut all_ids = vec![];
+        for handle in handles {
+            let ids = handle.join().unwrap();
+            // Each thread sees its own IDs strictly increasing
+            assert!(ids.windows(2).all(|w| w[1] > w[0]));
+            all_ids.extend(ids);
+        }
+
+        let count = all_ids.len();
+        all_ids.sort_unstable();
+        all_ids.dedup();
+        assert_eq!(all_ids.len(), count);
+    }
+
+    #[test]
+    fn test_unique_under_same_millis_contention() {
+        // The mock clock only moves when a thread overflows the 4 sequence
+        // numbers of a millisecond, so every thread races on the same state.
+        let (gen, _clock) = mock_generator(2, ClockRollbackPolicy::Fail);
+        assert_unique_across_threads(&Arc::new(gen), 8);
+    }
+
+    #[test]
+    fn test_unique_under_contention_while_borrowing() {
+        let (gen, clock) = mock_generator(2, ClockRollbackPolicy::Logical);
+        gen.next_id().unwrap();
+        clock.rewind(Duration::from_secs(10));
+        assert_unique_across_threads(&Arc::new(gen), 8);
+    }
+
+    #[test]
+    fn test_rollbacks_counted_once_per_id_under_contention() {
+        use crate::{Counter, MetricsRecorder};
+
+        let recorder = MetricsRecorder::new();
+        let (gen, clock) = mock_generator(2, ClockRollbackPolicy::Logical);
+        let gen = Arc::new(gen.with_metrics("user-id", recorder.clone()));
+        gen.next_id().unwrap();
+        clock.rewind(Duration::from_secs(10));
+        assert_unique_across_threads(&gen, 8);
+
+        // Every ID was borrowed; failed CAS attempts are not counted.
+        assert_eq!(gen.rollback_stats().borrowed, 16_000);
+        assert_eq!(
+            recorder
+                .snapshot()
+                .counter(Counter::ClockRollbacks, "user-id"),
+            16_000
+        );
+    }
+}
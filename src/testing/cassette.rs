@@ -0,0 +1,421 @@
+//! Recording of real HTTP traffic into cassette files and replay from them.
+
+#[cfg(feature = "async")]
+use std::future::{self, Future};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use super::request_target;
+#[cfg(feature = "async")]
+use crate::http::AsyncHttpTransport;
+use crate::http::{HttpClient, Method, Response};
+use crate::{Error, Result};
+
+/// Recorded request/response pairs, stored as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Exchanges in the order they happened.
+    pub interactions: Vec<Interaction>,
+}
+
+/// A request and the response it got.
+///
+/// Request headers are not recorded, so tokens stay out of cassette files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// HTTP method.
+    pub method: Method,
+
+    /// Path with the query string, e.g. `/v1/id/increment?key=order-id&size=5`.
+    pub target: String,
+
+    /// Body of a POST request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+
+    /// Response to the request.
+    pub response: RecordedResponse,
+}
+
+/// A response stored in a [`Cassette`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// HTTP status code.
+    pub status: u16,
+
+    /// Response headers as name/value pairs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+
+    /// Response body.
+    pub body: String,
+}
+
+impl Cassette {
+    /// Read a cassette file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if the file cannot be read and
+    /// [`Error::Serialization`] if it is not a cassette.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let input = std::fs::read_to_string(path).map_err(|err| {
+            Error::InvalidConfig(format!("cannot read cassette {}: {err}", path.display()))
+        })?;
+        Ok(serde_json::from_str(&input)?)
+    }
+
+    /// Write the cassette to a file, replacing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let output = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, output).map_err(|err| {
+            Error::InvalidConfig(format!("cannot write cassette {}: {err}", path.display()))
+        })
+    }
+}
+
+impl Interaction {
+    /// Check if this interaction answers a request, comparing the method, the
+    /// path and the query parameters in any order.
+    #[must_use]
+    pub fn matches(&self, method: Method, url: &str) -> bool {
+        self.method == method && normalize(&self.target) == normalize(request_target(url))
+    }
+}
+
+impl From<&Response> for RecordedResponse {
+    fn from(response: &Response) -> Self {
+        Self {
+            status: response.status,
+            headers: response.headers.clone(),
+            body: response.body.clone(),
+        }
+    }
+}
+
+impl From<RecordedResponse> for Response {
+    fn from(recorded: RecordedResponse) -> Self {
+        Self {
+            status: recorded.status,
+            body: recorded.body,
+            headers: recorded.headers,
+        }
+    }
+}
+
+/// Split a request target into its path and sorted query parameters.
+fn normalize(target: &str) -> (&str, Vec<&str>) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut params: Vec<&str> = query.split('&').filter(|param| !param.is_empty()).collect();
+    params.sort_unstable();
+    (path, params)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// An HTTP client that records the traffic of the client it wraps.
+///
+/// Every response is appended to the cassette, and the cassette file is
+/// rewritten after each request so that it is complete even if the test
+/// fails. Requests that fail in transport are not recorded.
+///
+/// # Panics
+///
+/// A request whose cassette file cannot be written panics, naming the file.
+/// The response of the wrapped client is never replaced by the error.
+///
+/// # Example
+///
+/// ```no_run
+/// use idbuilder::http::SyncHttpClient;
+/// use idbuilder::testing::RecordingHttpClient;
+/// use idbuilder::{ClientConfig, IdBuilderClient};
+///
+/// let config = ClientConfig::new("http://localhost:8080").with_key_token("my-key-token");
+/// let http = RecordingHttpClient::new(
+///     SyncHttpClient::new(config.timeout),
+///     "tests/cassettes/increment.json",
+/// );
+/// let client = IdBuilderClient::with_http_client(config, http);
+/// client.increment("order-id").generate(5)?;
+/// # Ok::<(), idbuilder::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct RecordingHttpClient<C> {
+    inner: C,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl<C> RecordingHttpClient<C> {
+    /// Record the traffic of `inner` into the cassette file at `path`.
+    pub fn new(inner: C, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    /// Get the interactions recorded so far.
+    #[must_use]
+    pub fn cassette(&self) -> Cassette {
+        lock(&self.cassette).clone()
+    }
+
+    /// Get the wrapped client.
+    pub const fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Append the outcome of a request to `target` and rewrite the cassette
+    /// file.
+    fn record(&self, method: Method, target: &str, body: Option<&str>, outcome: &Result<Response>) {
+        let Ok(response) = outcome else {
+            return;
+        };
+        let mut cassette = lock(&self.cassette);
+        cassette.interactions.push(Interaction {
+            method,
+            target: target.to_string(),
+            body: body.map(str::to_string),
+            response: response.into(),
+        });
+        if let Err(err) = cassette.save(&self.path) {
+            panic!("{err}");
+        }
+    }
+}
+
+impl<C: HttpClient> HttpClient for RecordingHttpClient<C> {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+        let outcome = self.inner.get(url, headers);
+        self.record(Method::Get, request_target(url), None, &outcome);
+        outcome
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response> {
+        let outcome = self.inner.post(url, headers, body);
+        self.record(Method::Post, request_target(url), Some(body), &outcome);
+        outcome
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C: AsyncHttpTransport + Sync> AsyncHttpTransport for RecordingHttpClient<C> {
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<Response>> + Send {
+        let target = request_target(url);
+        async move {
+            let outcome = self.inner.get(url, headers).await;
+            self.record(Method::Get, target, None, &outcome);
+            outcome
+        }
+    }
+
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> impl Future<Output = Result<Response>> + Send {
+        let target = request_target(url);
+        async move {
+            let outcome = self.inner.post(url, headers, body).await;
+            self.record(Method::Post, target, Some(body), &outcome);
+            outcome
+        }
+    }
+}
+
+/// An HTTP client that answers from a [`Cassette`] instead of the network.
+///
+/// Each request is answered by the first unused interaction with the same
+/// method, path and query parameters, so repeated requests get the recorded
+/// responses in order. Headers and bodies are not compared.
+///
+/// # Panics
+///
+/// A request that no unused interaction matches panics, naming the request.
+#[derive(Debug)]
+pub struct ReplayHttpClient {
+    remaining: Mutex<Vec<Interaction>>,
+}
+
+impl ReplayHttpClient {
+    /// Replay the interactions of `cassette`.
+    #[must_use]
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            remaining: Mutex::new(cassette.interactions),
+        }
+    }
+
+    /// Replay the interactions of a cassette file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a cassette.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Cassette::load(path).map(Self::new)
+    }
+
+    /// Get the interactions not replayed yet.
+    #[must_use]
+    pub fn remaining(&self) -> Vec<Interaction> {
+        lock(&self.remaining).clone()
+    }
+
+    /// Check that every interaction was replayed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if interactions are left, listing them.
+    pub fn assert_finished(&self) {
+        let remaining = self.remaining();
+        let unused: Vec<String> = remaining
+            .iter()
+            .map(|interaction| format!("{:?} {}", interaction.method, interaction.target))
+            .collect();
+        assert!(
+            unused.is_empty(),
+            "{} recorded interactions were not replayed: {}",
+            unused.len(),
+            unused.join(", ")
+        );
+    }
+
+    fn replay(&self, method: Method, url: &str) -> Response {
+        let mut remaining = lock(&self.remaining);
+        let Some(index) = remaining
+            .iter()
+            .position(|interaction| interaction.matches(method, url))
+        else {
+            let left = remaining.len();
+            drop(remaining);
+            panic!("unexpected {method:?} request to {url} ({left} recorded interactions left)");
+        };
+        remaining.remove(index).response.into()
+    }
+}
+
+impl HttpClient for ReplayHttpClient {
+    fn get(&self, url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+        Ok(self.replay(Method::Get, url))
+    }
+
+    fn post(&self, url: &str, _headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+        Ok(self.replay(Method::Post, url))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncHttpTransport for ReplayHttpClient {
+    fn get(
+        &self,
+        url: &str,
+        _headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<Response>> + Send {
+        future::ready(Ok(self.replay(Method::Get, url)))
+    }
+
+    fn post(
+        &self,
+        url: &str,
+        _headers: &[(&str, &str)],
+        _body: &str,
+    ) -> impl Future<Output = Result<Response>> + Send {
+        future::ready(Ok(self.replay(Method::Post, url)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeServer;
+    use crate::{ClientConfig, IdBuilderClient, IncrementKeyConfig};
+
+    fn config() -> ClientConfig {
+        ClientConfig::new("http://idbuilder.test").with_key_token("test-token")
+    }
+
+    #[test]
+    fn test_record_then_replay() {
+        let path =
+            std::env::temp_dir().join(format!("idbuilder-cassette-{}.json", std::process::id()));
+        let server = FakeServer::new()
+            .with_key_token("test-token")
+            .with_increment_key(IncrementKeyConfig::new("order-id"));
+
+        let recorder = RecordingHttpClient::new(server, &path);
+        let client = IdBuilderClient::with_http_client(config(), recorder);
+        assert_eq!(client.increment("order-id").generate(2).unwrap(), [1, 2]);
+        assert_eq!(client.increment("order-id").generate(2).unwrap(), [3, 4]);
+        assert!(client.increment("missing").generate(1).is_err());
+
+        let replay = ReplayHttpClient::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.remaining().len(), 3);
+        assert_eq!(replay.remaining()[0].method, Method::Get);
+
+        let client = IdBuilderClient::with_http_client(config(), replay);
+        assert_eq!(client.increment("order-id").generate(2).unwrap(), [1, 2]);
+        assert_eq!(client.increment("order-id").generate(2).unwrap(), [3, 4]);
+        assert!(client.increment("missing").generate(1).is_err());
+        client.http_client().assert_finished();
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot write cassette")]
+    fn test_unwritable_cassette_panics() {
+        let path = std::env::temp_dir()
+            .join(format!("idbuilder-missing-{}", std::process::id()))
+            .join("cassette.json");
+        let server = FakeServer::new()
+            .with_key_token("test-token")
+            .with_increment_key(IncrementKeyConfig::new("order-id"));
+
+        let client =
+            IdBuilderClient::with_http_client(config(), RecordingHttpClient::new(server, path));
+        let _ = client.increment("order-id").generate(1);
+    }
+
+    #[test]
+    fn test_query_order_is_ignored() {
+        let interaction = Interaction {
+            method: Method::Get,
+            target: "/v1/id/increment?key=a&size=2".to_string(),
+            body: None,
+            response: RecordedResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: String::new(),
+            },
+        };
+
+        assert!(interaction.matches(Method::Get, "http://host:8080/v1/id/increment?size=2&key=a"));
+        assert!(!interaction.matches(Method::Post, "/v1/id/increment?key=a&size=2"));
+        assert!(!interaction.matches(Method::Get, "/v1/id/increment?key=a&size=3"));
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected Get request to http://idbuilder.test/v1/id/increment")]
+    fn test_unexpected_request_panics() {
+        let client =
+            IdBuilderClient::with_http_client(config(), ReplayHttpClient::new(Cassette::default()));
+        let _ = client.increment("order-id").generate(1);
+    }
+}
@@ -0,0 +1,208 @@
+//! File configuration of a [`FakeServer`] for the stub server.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::FakeServer;
+use crate::types::admin::{FormattedKeyConfig, IncrementKeyConfig, SnowflakeKeyConfig};
+use crate::{Error, Result};
+
+/// Initial state of a [`FakeServer`], read from TOML or JSON.
+///
+/// Keys use the same fields as the admin API:
+///
+/// ```toml
+/// key_tokens = ["test-token"]
+/// admin_tokens = ["admin-token"]
+/// disabled = ["legacy-id"]
+///
+/// [[increment]]
+/// key = "order-id"
+/// start = 1000
+/// step = 1
+///
+/// [[formatted]]
+/// key = "invoice-id"
+/// pattern = "INV{yyyyMMdd}-{seq:4}"
+///
+/// [[snowflake]]
+/// key = "user-id"
+/// epoch = 1704067200000
+/// worker_bits = 10
+/// sequence_bits = 12
+///
+/// [quotas]
+/// order-id = 100000
+///
+/// [rate_limit]
+/// requests = 100
+/// window_ms = 1000
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StubConfig {
+    /// Tokens accepted as key tokens for every key.
+    pub key_tokens: Vec<String>,
+
+    /// Tokens accepted for the admin API.
+    pub admin_tokens: Vec<String>,
+
+    /// Auto-increment keys.
+    pub increment: Vec<IncrementKeyConfig>,
+
+    /// Formatted keys.
+    pub formatted: Vec<FormattedKeyConfig>,
+
+    /// Snowflake keys.
+    pub snowflake: Vec<SnowflakeKeyConfig>,
+
+    /// Keys that start out disabled.
+    pub disabled: Vec<String>,
+
+    /// Total number of IDs each key may issue.
+    pub quotas: BTreeMap<String, u64>,
+
+    /// Per-token rate limit.
+    pub rate_limit: Option<RateLimitConfig>,
+
+    /// How long a snowflake worker ID stays leased without a heartbeat, in
+    /// milliseconds.
+    pub lease_timeout_ms: Option<u64>,
+}
+
+/// Rate limit of a [`StubConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Requests allowed per window and token.
+    pub requests: u32,
+
+    /// Length of the window in milliseconds.
+    pub window_ms: u64,
+}
+
+impl StubConfig {
+    /// Parse a TOML configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if the configuration is invalid.
+    pub fn from_toml(input: &str) -> Result<Self> {
+        toml::from_str(input).map_err(|err| Error::InvalidConfig(err.to_string()))
+    }
+
+    /// Parse a JSON configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if the configuration is invalid.
+    pub fn from_json(input: &str) -> Result<Self> {
+        serde_json::from_str(input).map_err(|err| Error::InvalidConfig(err.to_string()))
+    }
+
+    /// Read a configuration file, as JSON if its name ends in `.json` and as
+    /// TOML otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if the file cannot be read or is
+    /// invalid.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let input = std::fs::read_to_string(path).map_err(|err| {
+            Error::InvalidConfig(format!("cannot read {}: {err}", path.display()))
+        })?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let parsed = if is_json {
+            Self::from_json(&input)
+        } else {
+            Self::from_toml(&input)
+        };
+        parsed.map_err(|err| match err {
+            Error::InvalidConfig(message) => {
+                Error::InvalidConfig(format!("{}: {message}", path.display()))
+            }
+            err => err,
+        })
+    }
+
+    /// Create a fake server in this state.
+    #[must_use]
+    pub fn into_server(self) -> FakeServer {
+        let mut server = FakeServer::new();
+        for token in self.key_tokens {
+            server = server.with_key_token(token);
+        }
+        for token in self.admin_tokens {
+            server = server.with_admin_token(token);
+        }
+        for config in self.increment {
+            server = server.with_increment_key(config);
+        }
+        for config in self.formatted {
+            server = server.with_formatted_key(config);
+        }
+        for config in self.snowflake {
+            server = server.with_snowflake_key(config);
+        }
+        for (key, ids) in self.quotas {
+            server = server.with_quota(key, ids);
+        }
+        if let Some(limit) = self.rate_limit {
+            server = server.with_rate_limit(limit.requests, Duration::from_millis(limit.window_ms));
+        }
+        if let Some(timeout) = self.lease_timeout_ms {
+            server = server.with_lease_timeout(Duration::from_millis(timeout));
+        }
+        for key in self.disabled {
+            server.disable_key(key);
+        }
+        server
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_and_json_agree() {
+        let toml = StubConfig::from_toml(
+            r#"
+            key_tokens = ["test-token"]
+
+            [[increment]]
+            key = "order-id"
+            start = 1000
+            step = 2
+
+            [rate_limit]
+            requests = 10
+            window_ms = 1500
+            "#,
+        )
+        .unwrap();
+        let json = StubConfig::from_json(
+            r#"{
+                "key_tokens": ["test-token"],
+                "increment": [{"key": "order-id", "start": 1000, "step": 2}],
+                "rate_limit": {"requests": 10, "window_ms": 1500}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(toml, json);
+        assert_eq!(toml.increment[0].start, 1000);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let err = StubConfig::from_toml("key_token = \"typo\"").unwrap_err();
+        assert!(matches!(err, Error::InvalidConfig(message) if message.contains("key_token")));
+    }
+}
@@ -0,0 +1,578 @@
+//! In-process fake `IDBuilder` server for tests, behind the `testing` feature.
+//!
+//! [`FakeServer`] implements [`HttpClient`] (and [`AsyncHttpTransport`] with
+//! the `async` feature) by answering requests from in-memory state instead of
+//! sending them, so a real [`IdBuilderClient`](crate::IdBuilderClient) can be
+//! tested against server behavior:
+//!
+//! - per-key increment counters with start, step and maximum value
+//! - formatted patterns such as `INV{yyyyMMdd}-{seq:4}`
+//! - snowflake worker IDs leased per key, with heartbeats and release
+//! - key and admin token checks, including tokens issued through the admin API
+//! - disabled keys, ID quotas and a per-token rate limit
+//! - injected faults such as error statuses, timeouts and refused connections
+//!
+//! Errors with a server code are answered with HTTP 200 and the code in the
+//! body, so that the client maps them to the matching [`Error`](crate::Error)
+//! variant.
+//!
+//! [`RecordingHttpClient`] captures the traffic of a real server into a
+//! [`Cassette`] file, and [`ReplayHttpClient`] answers from that file.
+//!
+//! # Example
+//!
+//! ```
+//! use idbuilder::testing::FakeServer;
+//! use idbuilder::{ClientConfig, IdBuilderClient, IncrementKeyConfig};
+//!
+//! let server = FakeServer::new()
+//!     .with_key_token("test-token")
+//!     .with_increment_key(IncrementKeyConfig::new("order-id").with_start(1000));
+//! let config = ClientConfig::new("http://idbuilder.test").with_key_token("test-token");
+//! let client = IdBuilderClient::with_http_client(config, server.clone());
+//!
+//! assert_eq!(client.increment("order-id").generate(2).unwrap(), [1000, 1001]);
+//! assert_eq!(server.issued("order-id"), 2);
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::{self, Future};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+#[cfg(feature = "async")]
+use crate::http::AsyncHttpTransport;
+use crate::http::{HttpClient, Method, Response};
+use crate::lease::LeaseOptions;
+use crate::types::admin::{FormattedKeyConfig, IncrementKeyConfig, SnowflakeKeyConfig};
+use crate::Result;
+
+mod cassette;
+#[cfg(feature = "stub")]
+mod config;
+mod pattern;
+mod state;
+#[cfg(feature = "stub")]
+mod stub;
+
+pub use cassette::{
+    Cassette, Interaction, RecordedResponse, RecordingHttpClient, ReplayHttpClient,
+};
+#[cfg(feature = "stub")]
+pub use config::{RateLimitConfig, StubConfig};
+#[cfg(feature = "stub")]
+pub use stub::StubServer;
+
+use state::{FaultRule, FormattedKey, IncrementKey, KeyState, SnowflakeKey, State};
+
+/// A failure injected into a request, see [`FakeServer::fail_next`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Answer with this HTTP status and an empty body.
+    Status(u16),
+
+    /// Answer with HTTP 200 and this server error code.
+    Code(i32),
+
+    /// Answer with HTTP 429 and the given `Retry-After` delay.
+    RateLimited(Option<Duration>),
+
+    /// Fail as if the connection was refused.
+    ConnectionRefused,
+
+    /// Fail as if the request timed out.
+    Timeout,
+}
+
+/// A request received by a [`FakeServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeRequest {
+    /// HTTP method.
+    pub method: Method,
+
+    /// Path without the query string, e.g. `/v1/id/increment`.
+    pub path: String,
+
+    /// Path with the query string.
+    pub url: String,
+
+    /// Value of the `Authorization` header.
+    pub token: Option<String>,
+
+    /// Body of a POST request.
+    pub body: Option<String>,
+}
+
+/// Stateful in-memory emulation of the `IDBuilder` server.
+///
+/// Clones share the same state, so a test can keep a handle to configure and
+/// inspect the server after handing it to a client. No token is accepted and
+/// no key exists until configured.
+#[derive(Clone)]
+pub struct FakeServer {
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeServer {
+    /// Create a server without tokens or keys, using the system clock.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                clock: Box::new(SystemClock),
+                key_tokens: HashSet::new(),
+                admin_tokens: HashSet::new(),
+                issued_tokens: Vec::new(),
+                increment: BTreeMap::new(),
+                formatted: BTreeMap::new(),
+                snowflake: BTreeMap::new(),
+                disabled: HashSet::new(),
+                quotas: HashMap::new(),
+                issued_ids: HashMap::new(),
+                rate_limit: None,
+                windows: HashMap::new(),
+                lease_timeout: LeaseOptions::DEFAULT_LEASE_TIMEOUT,
+                faults: VecDeque::new(),
+                requests: Vec::new(),
+            })),
+        }
+    }
+
+    /// Read time from `clock`, which drives leases, rate limit windows, token
+    /// expiry and the dates in formatted IDs.
+    #[must_use]
+    pub fn with_clock(self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.lock().clock = Box::new(clock);
+        self
+    }
+
+    /// Accept `token` as a key token for every key.
+    #[must_use]
+    pub fn with_key_token(self, token: impl Into<String>) -> Self {
+        self.lock().key_tokens.insert(token.into());
+        self
+    }
+
+    /// Accept `token` for the admin API.
+    #[must_use]
+    pub fn with_admin_token(self, token: impl Into<String>) -> Self {
+        self.lock().admin_tokens.insert(token.into());
+        self
+    }
+
+    /// Add an auto-increment key.
+    #[must_use]
+    pub fn with_increment_key(self, config: IncrementKeyConfig) -> Self {
+        let key = config.key.clone();
+        self.lock().increment.insert(key, IncrementKey::new(config));
+        self
+    }
+
+    /// Add a formatted key.
+    #[must_use]
+    pub fn with_formatted_key(self, config: FormattedKeyConfig) -> Self {
+        let key = config.key.clone();
+        self.lock().formatted.insert(key, FormattedKey::new(config));
+        self
+    }
+
+    /// Add a snowflake key.
+    #[must_use]
+    pub fn with_snowflake_key(self, config: SnowflakeKeyConfig) -> Self {
+        let key = config.key.clone();
+        self.lock().snowflake.insert(key, SnowflakeKey::new(config));
+        self
+    }
+
+    /// Limit `key` to `ids` IDs in total, after which batches fail with
+    /// [`Error::QuotaExceeded`](crate::Error::QuotaExceeded).
+    #[must_use]
+    pub fn with_quota(self, key: impl Into<String>, ids: u64) -> Self {
+        self.lock().quotas.insert(key.into(), ids);
+        self
+    }
+
+    /// Allow each token `requests` requests per `window`, answering HTTP 429
+    /// with `Retry-After` beyond that.
+    #[must_use]
+    pub fn with_rate_limit(self, requests: u32, window: Duration) -> Self {
+        self.lock().rate_limit = Some((requests, window));
+        self
+    }
+
+    /// Set how long a snowflake worker ID stays leased without a heartbeat.
+    #[must_use]
+    pub fn with_lease_timeout(self, timeout: Duration) -> Self {
+        self.lock().lease_timeout = timeout;
+        self
+    }
+
+    /// Disable a key, so that requests for it fail with
+    /// [`Error::KeyDisabled`](crate::Error::KeyDisabled).
+    pub fn disable_key(&self, key: impl Into<String>) {
+        self.lock().disabled.insert(key.into());
+    }
+
+    /// Enable a key disabled with [`disable_key`](Self::disable_key).
+    pub fn enable_key(&self, key: &str) {
+        self.lock().disabled.remove(key);
+    }
+
+    /// Fail the next request with `fault`.
+    pub fn fail_next(&self, fault: Fault) {
+        self.fail_requests("/", fault, 1);
+    }
+
+    /// Fail the next `times` requests whose path starts with `path` with
+    /// `fault`.
+    ///
+    /// Faults are checked in the order they were added, before tokens and
+    /// rate limits.
+    pub fn fail_requests(&self, path: impl Into<String>, fault: Fault, times: u32) {
+        if times == 0 {
+            return;
+        }
+        self.lock().faults.push_back(FaultRule {
+            fault,
+            path: path.into(),
+            times,
+        });
+    }
+
+    /// Get the requests received so far, in order.
+    #[must_use]
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// Forget the requests received so far.
+    pub fn clear_requests(&self) {
+        self.lock().requests.clear();
+    }
+
+    /// Get the number of increment or formatted IDs issued for `key`.
+    #[must_use]
+    pub fn issued(&self, key: &str) -> u64 {
+        self.lock().issued_ids.get(key).copied().unwrap_or(0)
+    }
+
+    /// Get the worker IDs currently leased for the snowflake `key`.
+    #[must_use]
+    pub fn leased_workers(&self, key: &str) -> Vec<u32> {
+        let state = self.lock();
+        let now = state.clock.now_millis().unwrap_or(i64::MIN);
+        state.snowflake.get(key).map_or_else(Vec::new, |key| {
+            key.workers
+                .iter()
+                .filter(|(_, until)| **until > now)
+                .map(|(worker_id, _)| *worker_id)
+                .collect()
+        })
+    }
+
+    /// Answer a request as the server would.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for an injected [`Fault::ConnectionRefused`] or
+    /// [`Fault::Timeout`], or if the clock cannot be read.
+    pub fn handle(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Option<&str>,
+    ) -> Result<Response> {
+        let token = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Authorization"))
+            .map(|(_, value)| *value);
+        self.lock().handle(method, url, token, body)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl Default for FakeServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FakeServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("FakeServer")
+            .field("increment", &state.increment.keys())
+            .field("formatted", &state.formatted.keys())
+            .field("snowflake", &state.snowflake.keys())
+            .field("requests", &state.requests.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl HttpClient for FakeServer {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response> {
+        self.handle(Method::Get, url, headers, None)
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response> {
+        self.handle(Method::Post, url, headers, Some(body))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncHttpTransport for FakeServer {
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> impl Future<Output = Result<Response>> + Send {
+        future::ready(self.handle(Method::Get, url, headers, None))
+    }
+
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> impl Future<Output = Result<Response>> + Send {
+        future::ready(self.handle(Method::Post, url, headers, Some(body)))
+    }
+}
+
+/// Strip the scheme and host from `url`, leaving the path and query.
+fn request_target(url: &str) -> &str {
+    url.find("://").map_or(url, |scheme| {
+        let rest = &url[scheme + 3..];
+        rest.find('/').map_or("/", |path| &rest[path..])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientConfig, Error, IdBuilderClient, IssueTokenRequest, MockClock};
+
+    const NOW: i64 = 1_705_326_330_123;
+
+    #[allow(clippy::literal_string_with_formatting_args)]
+    fn server() -> FakeServer {
+        FakeServer::new()
+            .with_clock(MockClock::new(NOW))
+            .with_key_token("key-token")
+            .with_admin_token("admin-token")
+            .with_increment_key(IncrementKeyConfig::new("order-id").with_max_value(5))
+            .with_formatted_key(FormattedKeyConfig::new(
+                "invoice-id",
+                "INV{yyyyMMdd}-{seq:4}",
+            ))
+            .with_snowflake_key(SnowflakeKeyConfig::new("user-id", NOW).with_bits(1, 12))
+    }
+
+    fn client(server: &FakeServer, key_token: &str) -> IdBuilderClient<FakeServer> {
+        let config = ClientConfig::new("http://idbuilder.test")
+            .with_key_token(key_token)
+            .with_admin_token("admin-token");
+        IdBuilderClient::with_http_client(config, server.clone())
+    }
+
+    #[test]
+    fn test_increment_counts_per_key() {
+        let server = server();
+        let client = client(&server, "key-token");
+
+        assert_eq!(client.increment("order-id").generate(3).unwrap(), [1, 2, 3]);
+        assert_eq!(client.increment("order-id").generate(2).unwrap(), [4, 5]);
+        assert!(matches!(
+            client.increment("order-id").generate(1),
+            Err(Error::SequenceExhausted(_))
+        ));
+        assert!(matches!(
+            client.increment("order-id").generate(0),
+            Err(Error::InvalidSize(_))
+        ));
+        assert!(matches!(
+            client.increment("missing").generate(1),
+            Err(Error::ConfigNotFound(_))
+        ));
+        assert_eq!(server.issued("order-id"), 5);
+    }
+
+    #[test]
+    fn test_formatted_renders_pattern() {
+        let server = server();
+        let client = client(&server, "key-token");
+
+        let ids = client.formatted("invoice-id").generate(2).unwrap();
+        assert_eq!(ids, ["INV20240115-0001", "INV20240115-0002"]);
+    }
+
+    #[test]
+    fn test_snowflake_workers_are_leased() {
+        let server = server();
+        let client = client(&server, "key-token");
+        let api = client.snowflake("user-id");
+
+        let first = api.get_config().unwrap();
+        let second = api.get_config().unwrap();
+        assert_eq!((first.worker_id, second.worker_id), (0, 1));
+        assert!(matches!(api.get_config(), Err(Error::SequenceExhausted(_))));
+
+        api.release(first.worker_id).unwrap();
+        assert_eq!(server.leased_workers("user-id"), [1]);
+        assert!(matches!(
+            api.heartbeat(first.worker_id),
+            Err(Error::LeaseLost(_))
+        ));
+        api.heartbeat(second.worker_id).unwrap();
+    }
+
+    #[test]
+    fn test_tokens_are_checked() {
+        let server = server();
+
+        assert!(matches!(
+            client(&server, "wrong").increment("order-id").generate(1),
+            Err(Error::Unauthorized)
+        ));
+
+        let admin = client(&server, "key-token");
+        let issued = admin
+            .admin()
+            .tokens()
+            .issue(&IssueTokenRequest::new(["invoice-id"]))
+            .unwrap();
+        let scoped = client(&server, &issued.token);
+        assert!(scoped.formatted("invoice-id").generate(1).is_ok());
+        assert!(matches!(
+            scoped.increment("order-id").generate(1),
+            Err(Error::Forbidden)
+        ));
+
+        admin.admin().tokens().revoke(&issued.info.id).unwrap();
+        assert!(matches!(
+            scoped.formatted("invoice-id").generate(1),
+            Err(Error::Unauthorized)
+        ));
+    }
+
+    #[test]
+    fn test_admin_manages_keys() {
+        let server = server();
+        let client = client(&server, "key-token");
+        let keys = client.admin().increment();
+
+        keys.create(&IncrementKeyConfig::new("ticket-id").with_start(100))
+            .unwrap();
+        assert!(keys.create(&IncrementKeyConfig::new("ticket-id")).is_err());
+        assert_eq!(client.increment("ticket-id").generate(1).unwrap(), [100]);
+
+        keys.update(&IncrementKeyConfig::new("ticket-id").with_step(10))
+            .unwrap();
+        assert_eq!(
+            client.increment("ticket-id").generate(2).unwrap(),
+            [101, 111]
+        );
+        assert_eq!(keys.list().unwrap().len(), 2);
+
+        keys.delete("ticket-id").unwrap();
+        assert!(matches!(
+            keys.get("ticket-id"),
+            Err(Error::ConfigNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_disabled_keys_and_quotas() {
+        let server = server().with_quota("invoice-id", 3);
+        let client = client(&server, "key-token");
+
+        server.disable_key("order-id");
+        assert!(matches!(
+            client.increment("order-id").generate(1),
+            Err(Error::KeyDisabled(_))
+        ));
+        server.enable_key("order-id");
+        assert!(client.increment("order-id").generate(1).is_ok());
+
+        client.formatted("invoice-id").generate(2).unwrap();
+        assert!(matches!(
+            client.formatted("invoice-id").generate(2),
+            Err(Error::QuotaExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_rate_limit_window() {
+        let clock = MockClock::new(NOW);
+        let server = server()
+            .with_clock(clock.clone())
+            .with_rate_limit(2, Duration::from_secs(10));
+        let client = client(&server, "key-token");
+
+        client.increment("order-id").generate(1).unwrap();
+        client.increment("order-id").generate(1).unwrap();
+        let Err(Error::RateLimited(limit)) = client.increment("order-id").generate(1) else {
+            panic!("expected a rate limit");
+        };
+        assert_eq!(limit.retry_after, Some(Duration::from_secs(10)));
+        assert_eq!(limit.limit, Some(2));
+
+        clock.advance(Duration::from_secs(10));
+        assert!(client.increment("order-id").generate(1).is_ok());
+    }
+
+    #[test]
+    fn test_injected_faults() {
+        let server = server();
+        let client = client(&server, "key-token");
+
+        server.fail_next(Fault::ConnectionRefused);
+        assert!(matches!(
+            client.increment("order-id").generate(1),
+            Err(Error::Http(_))
+        ));
+
+        server.fail_requests(
+            "/v1/id/formatted",
+            Fault::Code(crate::error::code::MAINTENANCE),
+            2,
+        );
+        assert!(client.increment("order-id").generate(1).is_ok());
+        for _ in 0..2 {
+            assert!(matches!(
+                client.formatted("invoice-id").generate(1),
+                Err(Error::Maintenance(_))
+            ));
+        }
+        assert!(client.formatted("invoice-id").generate(1).is_ok());
+
+        let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths.len(), 5);
+        assert_eq!(paths[0], "/v1/id/increment");
+    }
+
+    #[test]
+    fn test_retries_recover_from_faults() {
+        let server = server();
+        let config = ClientConfig::new("http://idbuilder.test")
+            .with_key_token("key-token")
+            .with_retries(2);
+        let client = IdBuilderClient::with_http_client(config, server.clone());
+
+        server.fail_next(Fault::Status(503));
+        assert_eq!(
+            client.snowflake("user-id").get_config().unwrap().worker_id,
+            0
+        );
+        assert_eq!(server.requests().len(), 2);
+    }
+}
@@ -0,0 +1,108 @@
+//! Rendering of formatted key patterns such as `INV{yyyyMMdd}-{seq:4}`.
+//!
+//! A placeholder is either the sequence, `{seq}` or `{seq:N}` zero-padded to
+//! `N` digits, or a UTC date made of `yyyy`, `yy`, `MM`, `dd`, `HH`, `mm` and
+//! `ss`. Anything else is copied as it is.
+
+use std::fmt::Write;
+
+/// Date and time fields, in the order they are matched.
+const DATE_TOKENS: [&str; 7] = ["yyyy", "yy", "MM", "dd", "HH", "mm", "ss"];
+
+/// Render `pattern` for sequence number `seq` at `millis` since the Unix epoch.
+pub fn render(pattern: &str, seq: u64, millis: i64) -> String {
+    let mut rendered = String::with_capacity(pattern.len() + 8);
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let placeholder = &rest[start + 1..start + len];
+        match placeholder.strip_prefix("seq") {
+            Some("") => rendered.push_str(&seq.to_string()),
+            Some(width) if width.starts_with(':') => {
+                let width = width[1..].parse().unwrap_or(0);
+                let _ = write!(rendered, "{seq:0width$}");
+            }
+            _ => render_date(&mut rendered, placeholder, millis),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn render_date(rendered: &mut String, format: &str, millis: i64) {
+    let secs = millis.div_euclid(1000);
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+
+    let mut rest = format;
+    while !rest.is_empty() {
+        let Some(token) = DATE_TOKENS.iter().find(|token| rest.starts_with(**token)) else {
+            let mut chars = rest.chars();
+            rendered.extend(chars.next());
+            rest = chars.as_str();
+            continue;
+        };
+        let _ = match *token {
+            "yyyy" => write!(rendered, "{year:04}"),
+            "yy" => write!(rendered, "{:02}", year.rem_euclid(100)),
+            "MM" => write!(rendered, "{month:02}"),
+            "dd" => write!(rendered, "{day:02}"),
+            "HH" => write!(rendered, "{:02}", time / 3600),
+            "mm" => write!(rendered, "{:02}", time / 60 % 60),
+            _ => write!(rendered, "{:02}", time % 60),
+        };
+        rest = &rest[token.len()..];
+    }
+}
+
+/// Convert days since the Unix epoch to a proleptic Gregorian date.
+const fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+#[allow(clippy::literal_string_with_formatting_args)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-15 13:45:30.123 UTC.
+    const MILLIS: i64 = 1_705_326_330_123;
+
+    #[test]
+    fn test_render_sequence_and_date() {
+        assert_eq!(
+            render("INV{yyyyMMdd}-{seq:4}", 7, MILLIS),
+            "INV20240115-0007"
+        );
+        assert_eq!(
+            render("{yy}/{HH:mm:ss}#{seq}", 42, MILLIS),
+            "24/13:45:30#42"
+        );
+        assert_eq!(render("plain-{seq:2}", 123, MILLIS), "plain-123");
+    }
+
+    #[test]
+    fn test_unterminated_placeholder_is_kept() {
+        assert_eq!(render("A{seq", 1, MILLIS), "A{seq");
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+}
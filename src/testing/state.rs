@@ -0,0 +1,578 @@
+//! State and request handling of the fake server.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::pattern;
+use super::{request_target, FakeRequest, Fault};
+use crate::api::urlencoding;
+use crate::clock::Clock;
+use crate::error::{code, HttpError};
+use crate::http::{Method, Response};
+use crate::types::admin::{
+    FormattedKeyConfig, IncrementKeyConfig, KeyConfig, KeyRequest, SnowflakeKeyConfig,
+};
+use crate::types::request::WorkerLeaseRequest;
+use crate::types::response::{FormattedIdResponse, IncrementIdResponse, SnowflakeIdResponse};
+use crate::types::token::{
+    IssueTokenRequest, IssuedToken, ScopeTokenRequest, TokenInfo, TokenRequest,
+};
+use crate::Result;
+
+/// Largest batch the server hands out.
+const MAX_BATCH_SIZE: u32 = 1000;
+
+/// An error answer: HTTP status, server code and message.
+type Rejection = (u16, i32, String);
+
+type Handled = std::result::Result<Response, Rejection>;
+
+/// Everything the fake server knows.
+pub struct State {
+    pub clock: Box<dyn Clock + Send + Sync>,
+    pub key_tokens: HashSet<String>,
+    pub admin_tokens: HashSet<String>,
+    pub issued_tokens: Vec<IssuedToken>,
+    pub increment: BTreeMap<String, IncrementKey>,
+    pub formatted: BTreeMap<String, FormattedKey>,
+    pub snowflake: BTreeMap<String, SnowflakeKey>,
+    pub disabled: HashSet<String>,
+    pub quotas: HashMap<String, u64>,
+    pub issued_ids: HashMap<String, u64>,
+    pub rate_limit: Option<(u32, Duration)>,
+    pub windows: HashMap<String, (i64, u32)>,
+    pub lease_timeout: Duration,
+    pub faults: VecDeque<FaultRule>,
+    pub requests: Vec<FakeRequest>,
+}
+
+pub struct IncrementKey {
+    pub config: IncrementKeyConfig,
+    pub next: i64,
+}
+
+pub struct FormattedKey {
+    pub config: FormattedKeyConfig,
+    pub seq: u64,
+}
+
+pub struct SnowflakeKey {
+    pub config: SnowflakeKeyConfig,
+    /// Held worker IDs and when their lease runs out, in milliseconds.
+    pub workers: BTreeMap<u32, i64>,
+}
+
+/// A fault injected into the next `times` requests whose path starts with
+/// `path`.
+pub struct FaultRule {
+    pub fault: Fault,
+    pub path: String,
+    pub times: u32,
+}
+
+/// State of a key of one ID type.
+pub trait KeyState {
+    type Config: KeyConfig;
+
+    fn new(config: Self::Config) -> Self;
+
+    fn config(&self) -> &Self::Config;
+
+    fn config_mut(&mut self) -> &mut Self::Config;
+}
+
+impl KeyState for IncrementKey {
+    type Config = IncrementKeyConfig;
+
+    fn new(config: IncrementKeyConfig) -> Self {
+        Self {
+            next: config.start,
+            config,
+        }
+    }
+
+    fn config(&self) -> &IncrementKeyConfig {
+        &self.config
+    }
+
+    fn config_mut(&mut self) -> &mut IncrementKeyConfig {
+        &mut self.config
+    }
+}
+
+impl KeyState for FormattedKey {
+    type Config = FormattedKeyConfig;
+
+    fn new(config: FormattedKeyConfig) -> Self {
+        Self { config, seq: 0 }
+    }
+
+    fn config(&self) -> &FormattedKeyConfig {
+        &self.config
+    }
+
+    fn config_mut(&mut self) -> &mut FormattedKeyConfig {
+        &mut self.config
+    }
+}
+
+impl KeyState for SnowflakeKey {
+    type Config = SnowflakeKeyConfig;
+
+    fn new(config: SnowflakeKeyConfig) -> Self {
+        Self {
+            config,
+            workers: BTreeMap::new(),
+        }
+    }
+
+    fn config(&self) -> &SnowflakeKeyConfig {
+        &self.config
+    }
+
+    fn config_mut(&mut self) -> &mut SnowflakeKeyConfig {
+        &mut self.config
+    }
+}
+
+impl State {
+    /// Answer a request to `url` authorized with `token`.
+    pub fn handle(
+        &mut self,
+        method: Method,
+        url: &str,
+        token: Option<&str>,
+        body: Option<&str>,
+    ) -> Result<Response> {
+        let now = self.clock.now_millis()?;
+        let target = request_target(url);
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        self.requests.push(FakeRequest {
+            method,
+            path: path.to_string(),
+            url: target.to_string(),
+            token: token.map(str::to_string),
+            body: body.map(str::to_string),
+        });
+
+        if let Some(fault) = self.take_fault(path) {
+            return fault.into_response();
+        }
+        if let Some(limited) = self.rate_limit(token.unwrap_or_default(), now) {
+            return Ok(limited);
+        }
+
+        let request = Incoming {
+            method,
+            path,
+            query,
+            token,
+            body: body.unwrap_or_default(),
+            now,
+        };
+        Ok(self
+            .route(&request)
+            .unwrap_or_else(|(status, code, message)| {
+                let body = serde_json::json!({ "code": code, "message": message, "data": null });
+                Response::new(status, body.to_string())
+            }))
+    }
+
+    fn take_fault(&mut self, path: &str) -> Option<Fault> {
+        let index = self
+            .faults
+            .iter()
+            .position(|rule| path.starts_with(&rule.path))?;
+        let rule = &mut self.faults[index];
+        rule.times -= 1;
+        let fault = rule.fault.clone();
+        if rule.times == 0 {
+            self.faults.remove(index);
+        }
+        Some(fault)
+    }
+
+    /// Count a request in the fixed window of `token`, answering 429 once the
+    /// window is used up.
+    fn rate_limit(&mut self, token: &str, now: i64) -> Option<Response> {
+        let (limit, window) = self.rate_limit?;
+        let window = i64::try_from(window.as_millis()).unwrap_or(i64::MAX);
+        let (start, count) = self.windows.entry(token.to_string()).or_insert((now, 0));
+        if now.saturating_sub(*start) >= window {
+            *start = now;
+            *count = 0;
+        }
+        if *count < limit {
+            *count += 1;
+            return None;
+        }
+        let retry_after = (*start + window - now + 999) / 1000;
+        Some(
+            Response::new(429, String::new())
+                .with_header("Retry-After", retry_after.max(1).to_string())
+                .with_header("X-RateLimit-Limit", limit.to_string())
+                .with_header("X-RateLimit-Remaining", "0"),
+        )
+    }
+
+    fn route(&mut self, request: &Incoming<'_>) -> Handled {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        match (request.method, segments.as_slice()) {
+            (Method::Get, ["v1", "id", "increment"]) => self.increment(request),
+            (Method::Get, ["v1", "id", "formatted"]) => self.formatted(request),
+            (Method::Get, ["v1", "id", "snowflake"]) => self.snowflake(request),
+            (Method::Post, ["v1", "id", "snowflake", action]) => self.lease(request, action),
+            (_, ["v1", "admin", ..]) => {
+                self.authorize_admin(request.token)?;
+                match &segments[2..] {
+                    ["token", action] => self.tokens(request, action),
+                    [kind] => self.manage_key(request, kind, "get"),
+                    [kind, action] => self.manage_key(request, kind, action),
+                    _ => Err(not_found("no such endpoint")),
+                }
+            }
+            _ => Err(not_found("no such endpoint")),
+        }
+    }
+
+    fn increment(&mut self, request: &Incoming<'_>) -> Handled {
+        let key = request.param("key");
+        let size = self.authorize_batch(request, &key)?;
+        let state = self
+            .increment
+            .get_mut(&key)
+            .ok_or_else(|| key_not_found(&key))?;
+        let step = state.config.step;
+        let last = state.next + step * (i64::from(size) - 1);
+        if state.config.max_value.is_some_and(|max| last > max) {
+            return Err(rejected(code::SEQUENCE_EXHAUSTED, "sequence exhausted"));
+        }
+        let ids = (0..i64::from(size))
+            .map(|i| state.next + step * i)
+            .collect();
+        state.next = last + step;
+        self.count_issued(&key, size);
+        Ok(success(&IncrementIdResponse { ids }))
+    }
+
+    fn formatted(&mut self, request: &Incoming<'_>) -> Handled {
+        let key = request.param("key");
+        let size = self.authorize_batch(request, &key)?;
+        let state = self
+            .formatted
+            .get_mut(&key)
+            .ok_or_else(|| key_not_found(&key))?;
+        let ids = (0..size)
+            .map(|_| {
+                state.seq += 1;
+                pattern::render(&state.config.pattern, state.seq, request.now)
+            })
+            .collect();
+        self.count_issued(&key, size);
+        Ok(success(&FormattedIdResponse { ids }))
+    }
+
+    fn snowflake(&mut self, request: &Incoming<'_>) -> Handled {
+        let key = request.param("key");
+        self.authorize_key(request.token, &key, request.now)?;
+        let expires_at = request.now + self.lease_timeout_millis();
+        let state = self
+            .snowflake
+            .get_mut(&key)
+            .ok_or_else(|| key_not_found(&key))?;
+        state.workers.retain(|_, until| *until > request.now);
+        let capacity = 1u64 << state.config.worker_bits.min(32);
+        let worker_id = (0..capacity)
+            .filter_map(|id| u32::try_from(id).ok())
+            .find(|id| !state.workers.contains_key(id))
+            .ok_or_else(|| rejected(code::SEQUENCE_EXHAUSTED, "no worker ID available"))?;
+        state.workers.insert(worker_id, expires_at);
+        Ok(success(&SnowflakeIdResponse {
+            worker_id,
+            epoch: state.config.epoch,
+            worker_bits: state.config.worker_bits,
+            sequence_bits: state.config.sequence_bits,
+        }))
+    }
+
+    fn lease(&mut self, request: &Incoming<'_>, action: &str) -> Handled {
+        let lease: WorkerLeaseRequest = request.json()?;
+        self.authorize_key(request.token, &lease.key, request.now)?;
+        let expires_at = request.now + self.lease_timeout_millis();
+        let state = self
+            .snowflake
+            .get_mut(&lease.key)
+            .ok_or_else(|| key_not_found(&lease.key))?;
+        let held = state
+            .workers
+            .get(&lease.worker_id)
+            .is_some_and(|until| *until > request.now);
+        if !held {
+            return Err((410, code::NOT_FOUND, "worker ID is not leased".to_string()));
+        }
+        match action {
+            "heartbeat" => state.workers.insert(lease.worker_id, expires_at),
+            "release" => state.workers.remove(&lease.worker_id),
+            _ => return Err(not_found("no such endpoint")),
+        };
+        Ok(success(&()))
+    }
+
+    fn manage_key(&mut self, request: &Incoming<'_>, kind: &str, action: &str) -> Handled {
+        match kind {
+            IncrementKeyConfig::KIND => manage(&mut self.increment, request, action),
+            FormattedKeyConfig::KIND => manage(&mut self.formatted, request, action),
+            SnowflakeKeyConfig::KIND => manage(&mut self.snowflake, request, action),
+            _ => Err(not_found("no such endpoint")),
+        }
+    }
+
+    fn tokens(&mut self, request: &Incoming<'_>, action: &str) -> Handled {
+        match (request.method, action) {
+            (Method::Post, "issue") => {
+                let issue: IssueTokenRequest = request.json()?;
+                let serial = self.issued_tokens.len() + 1;
+                let issued = IssuedToken {
+                    token: format!("fake-token-{serial}"),
+                    info: TokenInfo {
+                        id: format!("tok-{serial}"),
+                        allowed_keys: issue.allowed_keys,
+                        created_at: system_time(request.now),
+                        expires_at: issue.expires_at,
+                        description: issue.description,
+                    },
+                };
+                self.issued_tokens.push(issued.clone());
+                Ok(success(&issued))
+            }
+            (Method::Get, "list") => {
+                let infos: Vec<&TokenInfo> = self
+                    .issued_tokens
+                    .iter()
+                    .map(|issued| &issued.info)
+                    .collect();
+                Ok(success(&infos))
+            }
+            (Method::Post, "scope") => {
+                let scope: ScopeTokenRequest = request.json()?;
+                let issued = self
+                    .issued_tokens
+                    .iter_mut()
+                    .find(|issued| issued.info.id == scope.id)
+                    .ok_or_else(|| not_found("token not found"))?;
+                issued.info.allowed_keys = scope.allowed_keys;
+                Ok(success(&issued.info))
+            }
+            (Method::Post, "revoke") => {
+                let revoke: TokenRequest = request.json()?;
+                let index = self
+                    .issued_tokens
+                    .iter()
+                    .position(|issued| issued.info.id == revoke.id)
+                    .ok_or_else(|| not_found("token not found"))?;
+                self.issued_tokens.remove(index);
+                Ok(success(&()))
+            }
+            _ => Err(not_found("no such endpoint")),
+        }
+    }
+
+    /// Check the token, key and size of a batch request and return the size.
+    fn authorize_batch(
+        &self,
+        request: &Incoming<'_>,
+        key: &str,
+    ) -> std::result::Result<u32, Rejection> {
+        self.authorize_key(request.token, key, request.now)?;
+        let size = request
+            .param("size")
+            .parse()
+            .ok()
+            .filter(|size| (1..=MAX_BATCH_SIZE).contains(size))
+            .ok_or_else(|| {
+                let message = format!("size must be between 1 and {MAX_BATCH_SIZE}");
+                (200, code::INVALID_SIZE, message)
+            })?;
+        let issued = self.issued_ids.get(key).copied().unwrap_or(0);
+        if let Some(quota) = self.quotas.get(key) {
+            if issued + u64::from(size) > *quota {
+                return Err(rejected(code::QUOTA_EXCEEDED, "quota exceeded"));
+            }
+        }
+        Ok(size)
+    }
+
+    /// Check that `token` may generate IDs for `key`, and that it is enabled.
+    fn authorize_key(
+        &self,
+        token: Option<&str>,
+        key: &str,
+        now: i64,
+    ) -> std::result::Result<(), Rejection> {
+        let token = token.ok_or_else(unauthorized)?;
+        if !self.key_tokens.contains(token) {
+            let info = self
+                .issued_tokens
+                .iter()
+                .find(|issued| issued.token == token)
+                .map(|issued| &issued.info)
+                .ok_or_else(unauthorized)?;
+            if info.expires_at.is_some_and(|at| at <= system_time(now)) {
+                return Err(unauthorized());
+            }
+            if !info.allows(key) {
+                return Err((
+                    403,
+                    code::FORBIDDEN,
+                    "token may not access this key".to_string(),
+                ));
+            }
+        }
+        if self.disabled.contains(key) {
+            return Err(rejected(code::KEY_DISABLED, "key is disabled"));
+        }
+        Ok(())
+    }
+
+    fn authorize_admin(&self, token: Option<&str>) -> std::result::Result<(), Rejection> {
+        match token {
+            Some(token) if self.admin_tokens.contains(token) => Ok(()),
+            _ => Err(unauthorized()),
+        }
+    }
+
+    fn count_issued(&mut self, key: &str, size: u32) {
+        *self.issued_ids.entry(key.to_string()).or_default() += u64::from(size);
+    }
+
+    fn lease_timeout_millis(&self) -> i64 {
+        i64::try_from(self.lease_timeout.as_millis()).unwrap_or(i64::MAX)
+    }
+}
+
+/// Get, create, update, delete or list the keys of one type.
+fn manage<S: KeyState>(
+    keys: &mut BTreeMap<String, S>,
+    request: &Incoming<'_>,
+    action: &str,
+) -> Handled {
+    match (request.method, action) {
+        (Method::Get, "get") => {
+            let key = request.param("key");
+            let state = keys.get(&key).ok_or_else(|| key_not_found(&key))?;
+            Ok(success(state.config()))
+        }
+        (Method::Get, "list") => {
+            let configs: Vec<&S::Config> = keys.values().map(KeyState::config).collect();
+            Ok(success(&configs))
+        }
+        (Method::Post, "create") => {
+            let config: S::Config = request.json()?;
+            if keys.contains_key(config.key()) {
+                return Err(rejected(code::ALREADY_EXISTS, "key already exists"));
+            }
+            let body = success(&config);
+            keys.insert(config.key().to_string(), S::new(config));
+            Ok(body)
+        }
+        (Method::Post, "update") => {
+            let config: S::Config = request.json()?;
+            let state = keys
+                .get_mut(config.key())
+                .ok_or_else(|| key_not_found(config.key()))?;
+            // Counters and leases carry over to the new configuration.
+            let body = success(&config);
+            *state.config_mut() = config;
+            Ok(body)
+        }
+        (Method::Post, "delete") => {
+            let delete: KeyRequest = request.json()?;
+            keys.remove(&delete.key)
+                .ok_or_else(|| key_not_found(&delete.key))?;
+            Ok(success(&()))
+        }
+        _ => Err(not_found("no such endpoint")),
+    }
+}
+
+/// A parsed request.
+struct Incoming<'a> {
+    method: Method,
+    path: &'a str,
+    query: &'a str,
+    token: Option<&'a str>,
+    body: &'a str,
+    now: i64,
+}
+
+impl Incoming<'_> {
+    /// Get a decoded query parameter, empty if it is missing.
+    fn param(&self, name: &str) -> String {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| urlencoding::decode(value))
+            .unwrap_or_default()
+    }
+
+    fn json<T: DeserializeOwned>(&self) -> std::result::Result<T, Rejection> {
+        serde_json::from_str(self.body)
+            .map_err(|err| (400, code::INVALID_REQUEST, format!("invalid body: {err}")))
+    }
+}
+
+impl Fault {
+    fn into_response(self) -> Result<Response> {
+        match self {
+            Self::Status(status) => Ok(Response::new(status, String::new())),
+            Self::Code(code) => {
+                let body =
+                    serde_json::json!({ "code": code, "message": "injected fault", "data": null });
+                Ok(Response::new(200, body.to_string()))
+            }
+            Self::RateLimited(retry_after) => {
+                let response = Response::new(429, String::new());
+                Ok(match retry_after {
+                    Some(delay) => response.with_header("Retry-After", delay.as_secs().to_string()),
+                    None => response,
+                })
+            }
+            Self::ConnectionRefused => {
+                Err(HttpError::Connection("connection refused".to_string()).into())
+            }
+            Self::Timeout => Err(HttpError::Timeout.into()),
+        }
+    }
+}
+
+fn success<T: Serialize + ?Sized>(data: &T) -> Response {
+    let body = serde_json::json!({ "code": 0, "message": "success", "data": data });
+    Response::new(200, body.to_string())
+}
+
+/// Business errors are answered with HTTP 200, so that the client maps them
+/// by their code.
+fn rejected(code: i32, message: &str) -> Rejection {
+    (200, code, message.to_string())
+}
+
+fn not_found(message: &str) -> Rejection {
+    (404, code::NOT_FOUND, message.to_string())
+}
+
+fn key_not_found(key: &str) -> Rejection {
+    not_found(&format!("key not found: {key}"))
+}
+
+fn unauthorized() -> Rejection {
+    (401, code::UNAUTHORIZED, "invalid token".to_string())
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn system_time(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+}
@@ -0,0 +1,265 @@
+//! HTTP stub server answering from a [`FakeServer`].
+
+use std::fmt;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use super::FakeServer;
+use crate::error::HttpError;
+use crate::http::Method;
+use crate::Result;
+
+/// A [`FakeServer`] served over HTTP on a local port.
+///
+/// Requests are answered on a background thread until the stub server is
+/// dropped. Faults that fail the transport, such as [`Fault::Timeout`], are
+/// answered with HTTP 502.
+///
+/// [`Fault::Timeout`]: super::Fault::Timeout
+///
+/// # Example
+///
+/// ```no_run
+/// use idbuilder::testing::{FakeServer, StubServer};
+/// use idbuilder::{IdBuilderClient, IncrementKeyConfig, Result};
+///
+/// fn main() -> Result<()> {
+///     let fake = FakeServer::new()
+///         .with_key_token("test-token")
+///         .with_increment_key(IncrementKeyConfig::new("order-id"));
+///     let stub = StubServer::start(fake)?;
+///
+///     let client = IdBuilderClient::new(stub.url(), "test-token")?;
+///     assert_eq!(client.increment("order-id").generate(2)?, [1, 2]);
+///     Ok(())
+/// }
+/// ```
+pub struct StubServer {
+    fake: FakeServer,
+    http: Arc<tiny_http::Server>,
+    addr: SocketAddr,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl StubServer {
+    /// Serve `fake` on an unused port of `127.0.0.1`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no port can be bound.
+    pub fn start(fake: FakeServer) -> Result<Self> {
+        Self::bind("127.0.0.1:0", fake)
+    }
+
+    /// Serve `fake` on the given address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be bound.
+    pub fn bind(addr: impl ToSocketAddrs, fake: FakeServer) -> Result<Self> {
+        let http = tiny_http::Server::http(addr)
+            .map_err(|err| HttpError::Other(format!("cannot bind stub server: {err}")))?;
+        let addr = http
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| HttpError::Other("stub server is not bound to IP".to_string()))?;
+        let http = Arc::new(http);
+        let worker = {
+            let http = Arc::clone(&http);
+            let fake = fake.clone();
+            thread::spawn(move || {
+                for request in http.incoming_requests() {
+                    serve(&fake, request);
+                }
+            })
+        };
+        Ok(Self {
+            fake,
+            http,
+            addr,
+            worker: Some(worker),
+        })
+    }
+
+    /// Get the address the server listens on.
+    #[must_use]
+    pub const fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get the base URL of the server, e.g. `http://127.0.0.1:41234`.
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Get the fake server answering the requests, to configure or inspect it.
+    #[must_use]
+    pub const fn fake(&self) -> &FakeServer {
+        &self.fake
+    }
+
+    /// Block until the server stops, which it only does when it fails.
+    pub fn wait(mut self) {
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl fmt::Debug for StubServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StubServer")
+            .field("addr", &self.addr)
+            .field("fake", &self.fake)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.http.unblock();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Answer one request from `fake`.
+fn serve(fake: &FakeServer, mut request: tiny_http::Request) {
+    let method = match request.method() {
+        tiny_http::Method::Get => Method::Get,
+        tiny_http::Method::Post => Method::Post,
+        _ => {
+            let _ = request.respond(tiny_http::Response::empty(405));
+            return;
+        }
+    };
+    let mut body = String::new();
+    if request.as_reader().read_to_string(&mut body).is_err() {
+        let _ = request.respond(tiny_http::Response::empty(400));
+        return;
+    }
+
+    let headers: Vec<(String, String)> = request
+        .headers()
+        .iter()
+        .map(|header| (header.field.to_string(), header.value.to_string()))
+        .collect();
+    let headers: Vec<(&str, &str)> = headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let body = (method == Method::Post).then_some(body.as_str());
+
+    let Ok(response) = fake.handle(method, request.url(), &headers, body) else {
+        let _ = request.respond(tiny_http::Response::empty(502));
+        return;
+    };
+    let mut reply = tiny_http::Response::from_string(response.body)
+        .with_status_code(response.status)
+        .with_header(json_content_type());
+    for (name, value) in &response.headers {
+        if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            reply.add_header(header);
+        }
+    }
+    let _ = request.respond(reply);
+}
+
+fn json_content_type() -> tiny_http::Header {
+    tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("static header is valid")
+}
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::http::SyncHttpClient;
+    use crate::testing::{Fault, StubConfig};
+    use crate::{ClientConfig, Error, IdBuilderClient};
+
+    fn stub() -> StubServer {
+        let config = StubConfig::from_toml(
+            r#"
+            key_tokens = ["test-token"]
+
+            [[increment]]
+            key = "order-id"
+            start = 1000
+            step = 1
+
+            [[snowflake]]
+            key = "user-id"
+            epoch = 1704067200000
+            worker_bits = 10
+            sequence_bits = 12
+            "#,
+        )
+        .unwrap();
+        StubServer::start(config.into_server()).unwrap()
+    }
+
+    fn client(stub: &StubServer) -> IdBuilderClient<SyncHttpClient> {
+        let config = ClientConfig::new(stub.url()).with_key_token("test-token");
+        IdBuilderClient::from_config(config).unwrap()
+    }
+
+    #[test]
+    fn test_sync_client_against_stub() {
+        let stub = stub();
+        let client = client(&stub);
+
+        assert_eq!(
+            client.increment("order-id").generate(3).unwrap(),
+            [1000, 1001, 1002]
+        );
+        let config = client.snowflake("user-id").get_config().unwrap();
+        client
+            .snowflake("user-id")
+            .heartbeat(config.worker_id)
+            .unwrap();
+        assert!(matches!(
+            client.increment("missing").generate(1),
+            Err(Error::ConfigNotFound(_))
+        ));
+        assert_eq!(stub.fake().requests().len(), 4);
+    }
+
+    #[test]
+    fn test_headers_and_faults_pass_through() {
+        let stub = stub();
+        let client = client(&stub);
+
+        stub.fake()
+            .fail_next(Fault::RateLimited(Some(Duration::from_secs(7))));
+        let Err(Error::RateLimited(limit)) = client.increment("order-id").generate(1) else {
+            panic!("expected a rate limit");
+        };
+        assert_eq!(limit.retry_after, Some(Duration::from_secs(7)));
+
+        stub.fake().fail_next(Fault::Timeout);
+        assert!(matches!(
+            client.increment("order-id").generate(1),
+            Err(Error::Api { code: 502, .. })
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_client_against_stub() {
+        let stub = stub();
+        let config = ClientConfig::new(stub.url()).with_key_token("test-token");
+        let client = IdBuilderClient::from_config_async(config).unwrap();
+
+        let ids = client
+            .increment("order-id")
+            .generate_async(2)
+            .await
+            .unwrap();
+        assert_eq!(ids, [1000, 1001]);
+    }
+}
@@ -0,0 +1,309 @@
+//! Tracing instrumentation behind the `tracing` feature.
+//!
+//! Spans and events are emitted with the `idbuilder` target. Without the
+//! feature, [`Span`] is an empty stand-in and every function here does
+//! nothing, so call sites need no `cfg` attributes.
+
+#[cfg(feature = "async")]
+use std::future::Future;
+
+use crate::http::Response;
+use crate::Result;
+
+#[cfg(feature = "tracing")]
+pub use tracing::Span;
+
+/// Stand-in for `tracing::Span` without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub struct Span;
+
+/// Span around an API call, such as `generate` for `key`.
+///
+/// Records `status` (`ok` or the error kind) when the call finishes.
+#[cfg(feature = "tracing")]
+pub fn call_span(operation: &'static str, key: &str, size: Option<u32>) -> Span {
+    tracing::info_span!(
+        target: "idbuilder",
+        "idbuilder.call",
+        operation,
+        key,
+        size,
+        status = tracing::field::Empty,
+        error = tracing::field::Empty,
+    )
+}
+
+#[cfg(not(feature = "tracing"))]
+pub const fn call_span(_operation: &'static str, _key: &str, _size: Option<u32>) -> Span {
+    Span
+}
+
+/// Span around one attempt of a request against one endpoint.
+///
+/// Attempts are numbered from 1. Records the HTTP `status` of the response.
+#[cfg(feature = "tracing")]
+pub fn request_span(attempt: u32, endpoint: &str) -> Span {
+    tracing::debug_span!(
+        target: "idbuilder",
+        "idbuilder.request",
+        attempt,
+        endpoint,
+        status = tracing::field::Empty,
+        error = tracing::field::Empty,
+    )
+}
+
+#[cfg(not(feature = "tracing"))]
+pub const fn request_span(_attempt: u32, _endpoint: &str) -> Span {
+    Span
+}
+
+/// Run an API call in its span.
+#[cfg(feature = "tracing")]
+pub fn in_call<T>(span: &Span, call: impl FnOnce() -> Result<T>) -> Result<T> {
+    let outcome = span.in_scope(call);
+    record_call(span, &outcome);
+    outcome
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn in_call<T>(_span: &Span, call: impl FnOnce() -> Result<T>) -> Result<T> {
+    call()
+}
+
+/// Run an async API call in its span.
+#[cfg(feature = "async")]
+pub async fn in_call_async<T>(span: &Span, call: impl Future<Output = Result<T>>) -> Result<T> {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+
+        let outcome = call.instrument(span.clone()).await;
+        record_call(span, &outcome);
+        outcome
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = span;
+        call.await
+    }
+}
+
+/// Send a request in its span.
+#[cfg(feature = "tracing")]
+pub fn send(span: &Span, send: impl FnOnce() -> Result<Response>) -> Result<Response> {
+    let outcome = span.in_scope(send);
+    record_response(span, &outcome);
+    outcome
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn send(_span: &Span, send: impl FnOnce() -> Result<Response>) -> Result<Response> {
+    send()
+}
+
+/// Send an async request in its span.
+#[cfg(feature = "async")]
+pub async fn send_async(
+    span: Span,
+    send: impl Future<Output = Result<Response>>,
+) -> Result<Response> {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+
+        let outcome = send.instrument(span.clone()).await;
+        record_response(&span, &outcome);
+        outcome
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = span;
+        send.await
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn record_call<T>(span: &Span, outcome: &Result<T>) {
+    match outcome {
+        Ok(_) => {
+            span.record("status", "ok");
+        }
+        Err(err) => {
+            span.record("status", tracing::field::debug(err.kind()));
+            span.record("error", tracing::field::display(err));
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn record_response(span: &Span, outcome: &Result<Response>) {
+    match outcome {
+        Ok(response) => {
+            span.record("status", response.status);
+        }
+        Err(err) => {
+            span.record("error", tracing::field::display(err));
+        }
+    }
+}
+
+/// The snowflake clock is `behind_ms` behind the last issued timestamp.
+///
+/// `action` is how the rollback policy handles it: `fail`, `wait` or
+/// `borrow`. Borrowing happens for every ID while the clock is behind, so it
+/// is only logged at debug level.
+#[cfg(feature = "tracing")]
+pub fn clock_rollback(worker_id: u32, behind_ms: i64, action: &'static str) {
+    const MESSAGE: &str = "snowflake clock moved backwards";
+    if action == "borrow" {
+        tracing::debug!(target: "idbuilder", worker_id, behind_ms, action, MESSAGE);
+    } else {
+        tracing::warn!(target: "idbuilder", worker_id, behind_ms, action, MESSAGE);
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub const fn clock_rollback(_worker_id: u32, _behind_ms: i64, _action: &'static str) {}
+
+/// The snowflake sequence for `timestamp` is used up; waiting for the next
+/// millisecond.
+#[cfg(feature = "tracing")]
+pub fn sequence_wait(worker_id: u32, timestamp: i64) {
+    tracing::debug!(
+        target: "idbuilder",
+        worker_id,
+        timestamp,
+        "snowflake sequence overflow, waiting for next millisecond"
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+pub const fn sequence_wait(_worker_id: u32, _timestamp: i64) {}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::http::HttpClient;
+    use crate::{ClientConfig, IdBuilderClient, MockClock, SnowflakeGenerator};
+
+    use super::*;
+
+    /// Collects span and event fields as `name.field=value` strings.
+    #[derive(Clone, Default)]
+    struct Collector {
+        lines: Arc<Mutex<Vec<String>>>,
+        spans: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    struct Fields<'a>(&'a str, &'a Mutex<Vec<String>>);
+
+    impl Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            let line = format!("{}.{}={value:?}", self.0, field.name());
+            self.1.lock().unwrap().push(line);
+        }
+    }
+
+    impl Collector {
+        fn lines(&self) -> Vec<String> {
+            self.lines.lock().unwrap().clone()
+        }
+    }
+
+    impl Subscriber for Collector {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let name = span.metadata().name();
+            span.record(&mut Fields(name, &self.lines));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(name);
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let index = usize::try_from(span.into_u64()).unwrap() - 1;
+            let name = self.spans.lock().unwrap()[index];
+            values.record(&mut Fields(name, &self.lines));
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut Fields("event", &self.lines));
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    struct MockHttpClient;
+
+    impl HttpClient for MockHttpClient {
+        fn get(&self, _url: &str, _headers: &[(&str, &str)]) -> Result<Response> {
+            Ok(Response::new(
+                200,
+                r#"{"code":0,"message":"success","data":{"ids":[1,2,3]}}"#.to_string(),
+            ))
+        }
+
+        fn post(&self, _url: &str, _headers: &[(&str, &str)], _body: &str) -> Result<Response> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_generate_records_spans() {
+        let collector = Collector::default();
+        let config = ClientConfig::new("http://localhost:8080").with_key_token("test-token");
+        let client = IdBuilderClient::with_http_client(config, MockHttpClient);
+
+        tracing::subscriber::with_default(collector.clone(), || {
+            client.increment("order-id").generate(3).unwrap();
+        });
+
+        let lines = collector.lines();
+        for expected in [
+            "idbuilder.call.operation=\"increment.generate\"",
+            "idbuilder.call.key=\"order-id\"",
+            "idbuilder.call.size=3",
+            "idbuilder.call.status=\"ok\"",
+            "idbuilder.request.attempt=1",
+            "idbuilder.request.endpoint=\"http://localhost:8080\"",
+            "idbuilder.request.status=200",
+        ] {
+            assert!(
+                lines.iter().any(|line| line == expected),
+                "{expected} in {lines:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_clock_rollback_emits_event() {
+        let collector = Collector::default();
+        let clock = MockClock::new(1_704_067_200_123);
+        let generator =
+            SnowflakeGenerator::new(1, 1_704_067_200_000, 10, 12).with_clock(clock.clone());
+
+        tracing::subscriber::with_default(collector.clone(), || {
+            generator.next_id().unwrap();
+            clock.set(1_704_067_200_100);
+            generator.next_id().unwrap_err();
+        });
+
+        let lines = collector.lines();
+        assert!(lines.contains(&"event.behind_ms=23".to_string()));
+        assert!(lines.contains(&"event.action=\"fail\"".to_string()));
+    }
+}
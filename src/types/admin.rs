@@ -0,0 +1,223 @@
+//! Key configuration types for the admin API.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Configuration of an ID key managed through
+/// [`AdminApi`](crate::api::AdminApi).
+///
+/// Implemented by [`IncrementKeyConfig`], [`SnowflakeKeyConfig`] and
+/// [`FormattedKeyConfig`].
+pub trait KeyConfig: Serialize + DeserializeOwned {
+    /// Key type segment of the admin URLs, e.g. `increment`.
+    const KIND: &'static str;
+
+    /// Name of the key.
+    fn key(&self) -> &str;
+}
+
+/// Configuration of an auto-increment key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IncrementKeyConfig {
+    /// Name of the key.
+    pub key: String,
+
+    /// First ID handed out.
+    pub start: i64,
+
+    /// Difference between consecutive IDs.
+    pub step: i64,
+
+    /// Largest ID handed out before the sequence is exhausted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_value: Option<i64>,
+
+    /// Free-form description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl IncrementKeyConfig {
+    /// Default first ID.
+    pub const DEFAULT_START: i64 = 1;
+
+    /// Default step between IDs.
+    pub const DEFAULT_STEP: i64 = 1;
+
+    /// Create a configuration counting up by one from one.
+    #[must_use]
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            start: Self::DEFAULT_START,
+            step: Self::DEFAULT_STEP,
+            max_value: None,
+            description: None,
+        }
+    }
+
+    /// Set the first ID.
+    #[must_use]
+    pub const fn with_start(mut self, start: i64) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Set the step between IDs.
+    #[must_use]
+    pub const fn with_step(mut self, step: i64) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Set the largest ID.
+    #[must_use]
+    pub const fn with_max_value(mut self, max_value: i64) -> Self {
+        self.max_value = Some(max_value);
+        self
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl KeyConfig for IncrementKeyConfig {
+    const KIND: &'static str = "increment";
+
+    fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Configuration of a snowflake key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnowflakeKeyConfig {
+    /// Name of the key.
+    pub key: String,
+
+    /// Custom epoch timestamp in milliseconds.
+    pub epoch: i64,
+
+    /// Number of bits for worker ID.
+    pub worker_bits: u8,
+
+    /// Number of bits for sequence number.
+    pub sequence_bits: u8,
+
+    /// Free-form description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl SnowflakeKeyConfig {
+    /// Default number of worker bits.
+    pub const DEFAULT_WORKER_BITS: u8 = 10;
+
+    /// Default number of sequence bits.
+    pub const DEFAULT_SEQUENCE_BITS: u8 = 12;
+
+    /// Create a configuration with the default 10 worker and 12 sequence bits.
+    #[must_use]
+    pub fn new(key: impl Into<String>, epoch: i64) -> Self {
+        Self {
+            key: key.into(),
+            epoch,
+            worker_bits: Self::DEFAULT_WORKER_BITS,
+            sequence_bits: Self::DEFAULT_SEQUENCE_BITS,
+            description: None,
+        }
+    }
+
+    /// Set the number of worker and sequence bits.
+    #[must_use]
+    pub const fn with_bits(mut self, worker_bits: u8, sequence_bits: u8) -> Self {
+        self.worker_bits = worker_bits;
+        self.sequence_bits = sequence_bits;
+        self
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl KeyConfig for SnowflakeKeyConfig {
+    const KIND: &'static str = "snowflake";
+
+    fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Configuration of a formatted key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormattedKeyConfig {
+    /// Name of the key.
+    pub key: String,
+
+    /// Pattern the server renders IDs from, e.g. `INV{yyyyMMdd}-{seq:4}`.
+    pub pattern: String,
+
+    /// Free-form description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl FormattedKeyConfig {
+    /// Create a configuration with the given pattern.
+    #[must_use]
+    pub fn new(key: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            pattern: pattern.into(),
+            description: None,
+        }
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl KeyConfig for FormattedKeyConfig {
+    const KIND: &'static str = "formatted";
+
+    fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Request body naming a key, used to delete it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRequest {
+    /// Name of the key.
+    pub key: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optional_fields_are_omitted() {
+        let config = IncrementKeyConfig::new("order-id").with_step(2);
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"key":"order-id","start":1,"step":2}"#
+        );
+
+        let parsed: IncrementKeyConfig =
+            serde_json::from_str(r#"{"key":"order-id","start":1,"step":2}"#).unwrap();
+        assert_eq!(parsed, config);
+    }
+}
@@ -1,3 +1,6 @@
 //! Type definitions for API requests and responses.
 
+pub mod admin;
+pub mod request;
 pub mod response;
+pub mod token;
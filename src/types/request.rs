@@ -0,0 +1,13 @@
+//! Request body types for API calls.
+
+use serde::{Deserialize, Serialize};
+
+/// Request body identifying a snowflake worker lease.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerLeaseRequest {
+    /// Snowflake configuration key.
+    pub key: String,
+
+    /// Worker ID held by the client.
+    pub worker_id: u32,
+}
@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::SnowflakeGenerator;
+use crate::{SnowflakeGenerator, SnowflakeLayout};
 
 /// Standard API response wrapper.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +42,14 @@ impl<T> ApiResponse<T> {
             })
         }
     }
+
+    /// Convert to Result, mapping server error codes for the given key.
+    pub(crate) fn into_result_for(self, key: &str) -> crate::Result<T> {
+        if self.code != 0 {
+            return Err(crate::Error::from_api(key, self.code, self.message));
+        }
+        self.into_result()
+    }
 }
 
 /// Response for increment ID generation.
@@ -60,8 +68,11 @@ pub struct FormattedIdResponse {
 
 /// Response for snowflake configuration request.
 ///
-/// Contains all parameters needed for client-side ID generation.
+/// Contains all parameters needed for client-side ID generation. The bit
+/// counts are checked against [`SnowflakeLayout::MAX_BITS`] when the response
+/// is deserialized.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "SnowflakeIdFields")]
 pub struct SnowflakeIdResponse {
     /// Allocated worker ID for this client.
     pub worker_id: u32,
@@ -76,15 +87,61 @@ pub struct SnowflakeIdResponse {
     pub sequence_bits: u8,
 }
 
+/// Fields of a [`SnowflakeIdResponse`], checked before use.
+#[derive(Deserialize)]
+struct SnowflakeIdFields {
+    worker_id: u32,
+    epoch: i64,
+    worker_bits: u8,
+    sequence_bits: u8,
+}
+
+impl TryFrom<SnowflakeIdFields> for SnowflakeIdResponse {
+    type Error = crate::Error;
+
+    fn try_from(fields: SnowflakeIdFields) -> crate::Result<Self> {
+        SnowflakeLayout::try_new(fields.epoch, fields.worker_bits, fields.sequence_bits)?;
+        Ok(Self {
+            worker_id: fields.worker_id,
+            epoch: fields.epoch,
+            worker_bits: fields.worker_bits,
+            sequence_bits: fields.sequence_bits,
+        })
+    }
+}
+
 impl SnowflakeIdResponse {
+    /// Get the bit layout of IDs generated with this configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bit counts of a hand-built response exceed
+    /// [`SnowflakeLayout::MAX_BITS`].
+    #[must_use]
+    pub const fn layout(&self) -> SnowflakeLayout {
+        SnowflakeLayout::new(self.epoch, self.worker_bits, self.sequence_bits)
+    }
+
     /// Convert this response into a local snowflake generator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bit counts of a hand-built response exceed
+    /// [`SnowflakeLayout::MAX_BITS`].
     #[must_use]
     pub const fn into_generator(self) -> SnowflakeGenerator {
-        SnowflakeGenerator::new(
-            self.worker_id,
-            self.epoch,
-            self.worker_bits,
-            self.sequence_bits,
-        )
+        SnowflakeGenerator::with_layout(self.worker_id, self.layout())
+    }
+
+    /// Convert this response into a local snowflake generator, checking the
+    /// bit counts and that the worker ID fits them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`](crate::Error::InvalidConfig) if the
+    /// response does not describe a valid layout and worker ID.
+    pub fn try_into_generator(self) -> crate::Result<SnowflakeGenerator> {
+        let layout = SnowflakeLayout::try_new(self.epoch, self.worker_bits, self.sequence_bits)?;
+        SnowflakeGenerator::try_with_layout(self.worker_id, layout)
     }
 }
@@ -0,0 +1,215 @@
+//! Key token types for the admin API.
+//!
+//! Times are exchanged as milliseconds since the Unix epoch.
+
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+/// Metadata of a key token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenInfo {
+    /// Identifier of the token, used to scope or revoke it.
+    pub id: String,
+
+    /// Keys the token may generate IDs for.
+    pub allowed_keys: Vec<String>,
+
+    /// Time the token was issued.
+    #[serde(with = "millis")]
+    pub created_at: SystemTime,
+
+    /// Time the token stops being accepted, if it expires.
+    #[serde(
+        default,
+        with = "optional_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expires_at: Option<SystemTime>,
+
+    /// Free-form description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl TokenInfo {
+    /// Check if the token has expired.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= SystemTime::now())
+    }
+
+    /// Check if the token may generate IDs for the given key.
+    #[must_use]
+    pub fn allows(&self, key: &str) -> bool {
+        self.allowed_keys.iter().any(|allowed| allowed == key)
+    }
+}
+
+/// A newly issued key token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuedToken {
+    /// The token secret, to be used as a key token.
+    pub token: String,
+
+    /// Metadata of the token.
+    #[serde(flatten)]
+    pub info: TokenInfo,
+}
+
+/// Request body for issuing a key token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueTokenRequest {
+    /// Keys the token may generate IDs for.
+    pub allowed_keys: Vec<String>,
+
+    /// Time the token stops being accepted, or `None` for no expiry.
+    #[serde(
+        default,
+        with = "optional_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expires_at: Option<SystemTime>,
+
+    /// Free-form description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl IssueTokenRequest {
+    /// Create a request for a token scoped to the given keys.
+    #[must_use]
+    pub fn new<I, S>(allowed_keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed_keys: allowed_keys.into_iter().map(Into::into).collect(),
+            expires_at: None,
+            description: None,
+        }
+    }
+
+    /// Set the expiry time.
+    #[must_use]
+    pub const fn with_expires_at(mut self, expires_at: SystemTime) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Expire the token after the given duration from now.
+    #[must_use]
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        self.with_expires_at(SystemTime::now() + ttl)
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Request body for replacing the keys a token may access.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopeTokenRequest {
+    /// Identifier of the token.
+    pub id: String,
+
+    /// Keys the token may generate IDs for.
+    pub allowed_keys: Vec<String>,
+}
+
+/// Request body naming a token, used to revoke it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRequest {
+    /// Identifier of the token.
+    pub id: String,
+}
+
+/// (De)serialize a [`SystemTime`] as milliseconds since the Unix epoch.
+mod millis {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(serde::ser::Error::custom)?
+            .as_millis();
+        serializer.serialize_u64(millis as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let millis = u64::deserialize(deserializer)?;
+        Ok(UNIX_EPOCH + Duration::from_millis(millis))
+    }
+}
+
+/// (De)serialize an optional [`SystemTime`] as milliseconds since the Unix epoch.
+mod optional_millis {
+    use std::time::SystemTime;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::ref_option)]
+    pub fn serialize<S: Serializer>(
+        time: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => super::millis::serialize(time, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        #[derive(Deserialize)]
+        struct Millis(#[serde(with = "super::millis")] SystemTime);
+
+        Ok(Option::<Millis>::deserialize(deserializer)?.map(|Millis(time)| time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    #[test]
+    fn test_token_info_times_as_millis() {
+        let json = r#"{"id":"tok-1","allowed_keys":["order-id"],"created_at":1704067200123,"expires_at":1704067260123}"#;
+        let info: TokenInfo = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            info.created_at,
+            UNIX_EPOCH + Duration::from_millis(1_704_067_200_123)
+        );
+        assert_eq!(
+            info.expires_at,
+            Some(UNIX_EPOCH + Duration::from_millis(1_704_067_260_123))
+        );
+        assert!(info.is_expired());
+        assert!(info.allows("order-id"));
+        assert!(!info.allows("user-id"));
+        assert_eq!(serde_json::to_string(&info).unwrap(), json);
+    }
+
+    #[test]
+    fn test_issued_token_flattens_info() {
+        let json = r#"{"token":"secret","id":"tok-1","allowed_keys":[],"created_at":0}"#;
+        let issued: IssuedToken = serde_json::from_str(json).unwrap();
+
+        assert_eq!(issued.token, "secret");
+        assert_eq!(issued.info.id, "tok-1");
+        assert_eq!(issued.info.expires_at, None);
+    }
+}